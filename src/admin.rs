@@ -0,0 +1,149 @@
+use std::collections::HashMap;
+
+use serde_json::json;
+use warp::{Filter, Rejection};
+use warp::http::{HeaderMap, Method};
+
+use crate::cache;
+use crate::config;
+use crate::error::{error_reply, ProxyError};
+use crate::hot_cache;
+use crate::minio::minio_parser;
+use crate::minio::minio_pool::{self, MinioPool};
+use crate::minio::presign_cache;
+use crate::replication;
+use crate::trace;
+use crate::utils::constant_time_eq;
+
+// 在单独端口启动管理接口，未配置 admin 时不启动
+pub async fn serve() {
+    let admin = match config::current().admin.clone() {
+        Some(admin) => admin,
+        None => return,
+    };
+    let bind = admin.bind.as_deref().unwrap_or("127.0.0.1");
+    let addr: std::net::IpAddr = match bind.parse() {
+        Ok(addr) => addr,
+        Err(e) => {
+            log::error!("Invalid admin bind address {}: {}", bind, e);
+            return;
+        }
+    };
+
+    let route = warp::path::full()
+        .and(warp::query::<HashMap<String, String>>())
+        .and(warp::method())
+        .and(warp::header::headers_cloned())
+        .and(trace::request_id())
+        .and_then(process);
+
+    log::info!("Admin API listening on {}:{}", addr, admin.port);
+    warp::serve(route).run((addr, admin.port)).await;
+}
+
+async fn process(
+    path: warp::path::FullPath,
+    params: HashMap<String, String>,
+    method: Method,
+    headers: HeaderMap,
+    request_id: String,
+) -> Result<Box<dyn warp::Reply>, Rejection> {
+    log::info!("Admin access: {} {}", method, path.as_str());
+    let reply = match handle(path.as_str(), &params, &method, &headers).await {
+        Ok(value) => Box::new(warp::reply::json(&value)) as Box<dyn warp::Reply>,
+        Err(e) => error_reply(e, &request_id)?,
+    };
+    Ok(Box::new(warp::reply::with_header(reply, config::REQUEST_ID_HEADER, request_id)))
+}
+
+async fn handle(path: &str, params: &HashMap<String, String>, method: &Method, headers: &HeaderMap) -> Result<serde_json::Value, ProxyError> {
+    if !authorized(headers) {
+        return Err(ProxyError::Unauthorized);
+    }
+
+    match (method.as_str(), path.trim_end_matches('/')) {
+        ("GET", "/admin/powers") => Ok(json!(powers())),
+        ("GET", "/admin/minio") => Ok(json!(MinioPool::statuses().await)),
+        ("POST", "/admin/minio/health-check") => {
+            MinioPool::check_health().await;
+            Ok(json!(MinioPool::statuses().await))
+        }
+        ("POST", "/admin/minio/drain") => {
+            let param = |name: &str| {
+                params
+                    .get(name)
+                    .ok_or_else(|| ProxyError::BadRequest(format!("Missing query parameter `{}`", name)))
+            };
+            let (power, endpoint) = (param("power")?, param("endpoint")?);
+            // drained=false 表示恢复实例
+            let drained = params.get("drained").is_none_or(|v| v != "false");
+            if MinioPool::set_drained(power, endpoint, drained).await == 0 {
+                return Err(ProxyError::NotFound(format!("No MinIO instance {} in {}", endpoint, power)));
+            }
+            Ok(json!({ "power": power, "endpoint": endpoint, "drained": drained }))
+        }
+        ("GET", "/admin/redis") => Ok(json!(cache::redis_pool_stats().await)),
+        ("GET", "/admin/replication") => Ok(json!(replication::status().await)),
+        ("GET", "/admin/presign") => Ok(json!(presign_cache::stats())),
+        ("GET", "/admin/hot-cache") => Ok(json!(hot_cache::stats())),
+        ("POST", "/admin/hot-cache/purge") => {
+            let param = |name: &str| params.get(name).map(String::as_str);
+            let purged = hot_cache::purge(param("power"), param("key"), param("prefix"));
+            Ok(json!({ "purged": purged }))
+        }
+        ("POST", "/admin/bucket-cache/flush") => Ok(json!({ "flushed": minio_parser::flush_bucket_cache().await })),
+        ("POST", "/admin/config/reload") => {
+            let config = config::reread().map_err(|errors| {
+                ProxyError::Config(format!("Config has {} error(s), keeping current config", errors))
+            })?;
+            // 先创建全部 MinIO 连接池，失败时不替换配置
+            let pools = minio_pool::create_pools(&config)
+                .map_err(|e| ProxyError::Config(format!("{}, keeping current config", e)))?;
+            config::replace(config);
+            cache::initialize_redis_pools();
+            minio_pool::install_pools(pools).await;
+            minio_parser::flush_bucket_cache().await;
+            hot_cache::purge(None, None, None);
+            presign_cache::flush();
+            Ok(json!({ "reloaded": true, "powers": powers() }))
+        }
+        _ => Err(ProxyError::NotFound(format!("No admin endpoint {} {}", method, path))),
+    }
+}
+
+// power 及其解析后的桶名，default 表示默认配置
+fn powers() -> Vec<serde_json::Value> {
+    let config = config::current();
+    let default_bucket = config.default.bucket_name.clone();
+    let mut powers = vec![json!({ "power": "default", "bucket": default_bucket })];
+    if let Some(power) = &config.power {
+        let mut keys: Vec<&String> = power.keys().collect();
+        keys.sort();
+        for key in keys {
+            let power_config = &power[key];
+            let bucket = config.bucket_name(key.clone()).or_else(|| default_bucket.clone());
+            powers.push(json!({
+                "power": key,
+                "bucket": bucket,
+                "buckets": power_config.buckets,
+                "bucket_mode": power_config.bucket_mode,
+                "backend": power_config.backend,
+            }));
+        }
+    }
+    powers
+}
+
+fn authorized(headers: &HeaderMap) -> bool {
+    let config = config::current();
+    let token = match &config.admin {
+        Some(admin) => admin.token.expose(),
+        None => return false,
+    };
+    let provided = headers
+        .get("authorization")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .unwrap_or("");
+    constant_time_eq(provided.as_bytes(), token.as_bytes())
+}
@@ -0,0 +1,226 @@
+use std::io;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use futures_util::{Stream, StreamExt};
+use minio::s3::args::{CopyObjectArgs, CopySource, GetObjectArgs, ListObjectsV2Args, PutObjectApiArgs, RemoveObjectArgs, StatObjectArgs};
+use minio::s3::client::Client;
+use minio::s3::error::Error as MinioError;
+use minio::s3::utils::Multimap;
+use r2d2::PooledConnection;
+use tracing::Instrument;
+use warp::http::{HeaderMap, StatusCode};
+
+use crate::backend::{Backend, ListPage, ListQuery, ObjectEntry, ObjectMeta, ObjectResponse, CONDITIONAL_HEADERS};
+use crate::config;
+use crate::config::fetch_config::FetchMode;
+use crate::error::ProxyError;
+use crate::minio::minio_parser;
+use crate::minio::minio_pool::{self, MinioPool};
+use crate::minio::r2d2_minio::MinioConnectionManager;
+
+// 通过 power 的 MinIO 连接池访问对象，读取时按 fetch 配置生成预签名地址转发或直接 GET
+pub struct MinioBackend {
+    config_key: String,
+}
+
+impl MinioBackend {
+    pub fn new(config_key: &str) -> Self {
+        MinioBackend { config_key: config_key.to_string() }
+    }
+
+    // 从 power 的连接池中取出一个客户端
+    async fn client(&self) -> Result<PooledConnection<MinioConnectionManager>, ProxyError> {
+        let pool = MinioPool::get_minio_client(&self.config_key).await?;
+        minio_pool::checkout(&pool).await
+    }
+
+    // 生成预签名地址，通过代理的 HTTP 客户端转发 Range 和条件请求头
+    async fn get_presigned(&self, bucket: &str, key: &str, headers: &HeaderMap) -> Result<Upstream, ProxyError> {
+        let link = minio_parser::get_generate_link_by_stored_key(&self.config_key, Some(bucket), key).await?;
+
+        let mut client_request = crate::CLIENT.get(&link);
+        for name in CONDITIONAL_HEADERS {
+            if let Some(value) = headers.get(name) {
+                client_request = client_request.header(name, value);
+            }
+        }
+
+        // 发送请求并获取异步的响应流
+        Ok(Upstream::Response(client_request.send().await?))
+    }
+
+    // 使用连接池中的客户端直接 GET，请求头参与签名；克隆的客户端共用原客户端的连接，取出后立即放回，并发读取不会占满连接池
+    async fn get_direct(&self, bucket: &str, key: &str, headers: &HeaderMap) -> Result<Upstream, ProxyError> {
+        let client = Client::clone(&*self.client().await?);
+        let mut extra_headers = Multimap::new();
+        for name in CONDITIONAL_HEADERS {
+            if let Some(value) = headers.get(name).and_then(|value| value.to_str().ok()) {
+                extra_headers.insert(name.to_string(), value.to_string());
+            }
+        }
+        let mut args = GetObjectArgs::new(bucket, key)?;
+        args.extra_headers = Some(&extra_headers);
+        match client.get_object(&args).await {
+            Ok(response) => Ok(Upstream::Response(response)),
+            // minio 客户端把 304 当作错误，且不返回响应头
+            Err(MinioError::ServerError(304)) => Ok(Upstream::NotModified),
+            Err(e) => Err(e.into()),
+        }
+    }
+}
+
+// 上游的响应；direct 模式下条件请求命中时只能得到 304，没有响应头
+enum Upstream {
+    Response(reqwest::Response),
+    NotModified,
+}
+
+// 两次收到数据的间隔超过 timeout 时以错误结束响应流
+fn read_timeout(response: reqwest::Response, timeout: Duration) -> impl Stream<Item = Result<Bytes, io::Error>> {
+    futures_util::stream::unfold(Some(response.bytes_stream()), move |stream| async move {
+        let mut stream = stream?;
+        match tokio::time::timeout(timeout, stream.next()).await {
+            Ok(Some(Ok(chunk))) => Some((Ok(chunk), Some(stream))),
+            Ok(Some(Err(e))) => Some((Err(io::Error::other(e)), None)),
+            Ok(None) => None,
+            Err(_) => {
+                log::warn!("Upstream read timed out after {}ms", timeout.as_millis());
+                Some((Err(io::Error::new(io::ErrorKind::TimedOut, "upstream read timed out")), None))
+            }
+        }
+    })
+}
+
+#[async_trait]
+impl Backend for MinioBackend {
+    async fn get(&self, bucket: &str, key: &str, headers: &HeaderMap) -> Result<ObjectResponse, ProxyError> {
+        let fetch = config::current()
+            .power_config(&self.config_key)
+            .and_then(|power| power.fetch.clone())
+            .unwrap_or_default();
+        let request = async {
+            match fetch.mode {
+                FetchMode::Presign => self.get_presigned(bucket, key, headers).await,
+                FetchMode::Direct => self.get_direct(bucket, key, headers).await,
+            }
+        }
+        .instrument(tracing::info_span!("upstream_fetch"));
+        // 超时覆盖发出请求到收到响应头的过程，包括建立连接
+        let response = match fetch.header_timeout_ms {
+            Some(ms) => tokio::time::timeout(Duration::from_millis(ms), request)
+                .await
+                .map_err(|_| ProxyError::Timeout(format!("No response from upstream of {} within {}ms", self.config_key, ms)))??,
+            None => request.await?,
+        };
+        let response = match response {
+            Upstream::Response(response) => response,
+            Upstream::NotModified => {
+                return Ok(ObjectResponse { status: StatusCode::NOT_MODIFIED, headers: HeaderMap::new(), body: warp::hyper::Body::empty() })
+            }
+        };
+        let status = response.status();
+
+        // 上游返回错误时解析 S3 错误体，转换为统一的错误响应
+        if status.is_client_error() || status.is_server_error() {
+            let body = response.text().await.unwrap_or_default();
+            return Err(ProxyError::from_upstream_response(status, &body));
+        }
+        let headers = response.headers().clone();
+        // 使用 `hyper::Body::wrap_stream` 将响应流转换为 warp 可以发送的 Body
+        let body = match fetch.read_timeout_ms {
+            Some(ms) => warp::hyper::Body::wrap_stream(read_timeout(response, Duration::from_millis(ms))),
+            None => warp::hyper::Body::wrap_stream(response.bytes_stream()),
+        };
+        Ok(ObjectResponse { status, headers, body })
+    }
+
+    async fn stat(&self, bucket: &str, key: &str) -> Result<ObjectMeta, ProxyError> {
+        let client = self.client().await?;
+        let args = StatObjectArgs::new(bucket, key)?;
+        let stat = client.stat_object(&args).await?;
+        let metadata = stat
+            .headers
+            .iter()
+            .filter(|(name, _)| name.as_str().starts_with("x-amz-meta-"))
+            .filter_map(|(name, value)| Some((name.to_string(), value.to_str().ok()?.to_string())))
+            .collect();
+        Ok(ObjectMeta {
+            size: stat.size as u64,
+            etag: stat.etag,
+            last_modified: stat.last_modified,
+            content_type: stat.headers.get("content-type").and_then(|v| v.to_str().ok()).map(str::to_string),
+            metadata,
+        })
+    }
+
+    async fn list_page(&self, bucket: &str, query: ListQuery<'_>) -> Result<ListPage, ProxyError> {
+        let client = self.client().await?;
+        let mut args = ListObjectsV2Args::new(bucket)?;
+        args.prefix = Some(query.prefix).filter(|prefix| !prefix.is_empty());
+        args.delimiter = query.delimiter;
+        args.max_keys = query.max_keys;
+        args.continuation_token = query.continuation_token;
+        args.start_after = query.start_after;
+        let response = client.list_objects_v2(&args).await?;
+        let entries = response
+            .contents
+            .into_iter()
+            .map(|item| ObjectEntry {
+                name: item.name,
+                is_prefix: item.is_prefix,
+                size: item.size.unwrap_or(0) as u64,
+                last_modified: item.last_modified,
+                etag: item.etag,
+            })
+            .collect();
+        Ok(ListPage {
+            entries,
+            is_truncated: response.is_truncated,
+            next_continuation_token: response.next_continuation_token,
+        })
+    }
+
+    async fn put(&self, bucket: &str, key: &str, body: Bytes, headers: Multimap) -> Result<String, ProxyError> {
+        let client = self.client().await?;
+        let (bucket, key) = (bucket.to_string(), key.to_string());
+        run_blocking(move || async move {
+            let mut args = PutObjectApiArgs::new(&bucket, &key, &body)?;
+            args.headers = Some(&headers);
+            Ok(client.put_object_api(&args).await?.etag)
+        })
+        .await
+    }
+
+    async fn copy(&self, bucket: &str, source: &str, target: &str) -> Result<(), ProxyError> {
+        let client = self.client().await?;
+        let (bucket, source, target) = (bucket.to_string(), source.to_string(), target.to_string());
+        run_blocking(move || async move {
+            let args = CopyObjectArgs::new(&bucket, &target, CopySource::new(&bucket, &source)?)?;
+            client.copy_object(&args).await?;
+            Ok(())
+        })
+        .await
+    }
+
+    async fn remove(&self, bucket: &str, key: &str) -> Result<(), ProxyError> {
+        let client = self.client().await?;
+        let args = RemoveObjectArgs::new(bucket, key)?;
+        client.remove_object(&args).await?;
+        Ok(())
+    }
+}
+
+// minio 0.1.0 的上传、复制参数中含有非 Sync 的 Sse 引用，不能出现在 Send 的 future 中，放到阻塞线程执行
+async fn run_blocking<T, F, Fut>(f: F) -> Result<T, ProxyError>
+where
+    T: Send + 'static,
+    F: FnOnce() -> Fut + Send + 'static,
+    Fut: std::future::Future<Output = Result<T, ProxyError>>,
+{
+    let handle = tokio::runtime::Handle::current();
+    tokio::task::spawn_blocking(move || handle.block_on(f()))
+        .await
+        .map_err(|e| ProxyError::Upstream(e.to_string()))?
+}
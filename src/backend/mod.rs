@@ -0,0 +1,176 @@
+use async_trait::async_trait;
+use bytes::Bytes;
+use minio::s3::utils::{from_http_header_value, to_http_header_value, Multimap, UtcTime};
+use warp::http::{HeaderMap, HeaderName, HeaderValue, StatusCode};
+
+use crate::config;
+use crate::config::power_config::{BackendKind, BucketMode};
+use crate::config::warp_config::WarpConfig;
+use crate::error::ProxyError;
+
+pub(crate) mod fs_backend;
+pub(crate) mod minio_backend;
+
+// 读取对象时转发给后端的条件请求头
+pub const CONDITIONAL_HEADERS: [&str; 5] = ["range", "if-match", "if-none-match", "if-modified-since", "if-unmodified-since"];
+
+// 上传对象时随对象保存的请求头，另外保留 x-amz-meta-*
+const STORED_HEADERS: [&str; 5] = ["content-type", "content-encoding", "content-disposition", "content-language", "cache-control"];
+
+// 读取对象的响应，状态码为 200、206 或 304
+pub struct ObjectResponse {
+    pub(crate) status: StatusCode,
+    pub(crate) headers: HeaderMap,
+    pub(crate) body: warp::hyper::Body,
+}
+
+// 对象的元信息
+pub struct ObjectMeta {
+    pub(crate) size: u64,
+    pub(crate) etag: String,
+    pub(crate) last_modified: Option<UtcTime>,
+    pub(crate) content_type: Option<String>,
+    // x-amz-meta-* 自定义元数据
+    pub(crate) metadata: Vec<(String, String)>,
+}
+
+impl ObjectMeta {
+    // HEAD 请求的响应头
+    pub fn headers(&self) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert("content-length", HeaderValue::from(self.size));
+        let mut insert = |name: &str, value: &str| {
+            if let (Ok(name), Ok(value)) = (HeaderName::from_bytes(name.as_bytes()), HeaderValue::from_str(value)) {
+                headers.insert(name, value);
+            }
+        };
+        insert("etag", &format!("\"{}\"", self.etag.trim_matches('"')));
+        if let Some(last_modified) = self.last_modified {
+            insert("last-modified", &to_http_header_value(last_modified));
+        }
+        if let Some(content_type) = &self.content_type {
+            insert("content-type", content_type);
+        }
+        for (name, value) in &self.metadata {
+            insert(name, value);
+        }
+        headers
+    }
+}
+
+// 列表中的一项，is_prefix 为 true 时是按 delimiter 折叠后的公共前缀
+pub struct ObjectEntry {
+    pub(crate) name: String,
+    pub(crate) is_prefix: bool,
+    pub(crate) size: u64,
+    pub(crate) last_modified: Option<UtcTime>,
+    pub(crate) etag: Option<String>,
+}
+
+// 分页列出对象的参数
+#[derive(Default)]
+pub struct ListQuery<'a> {
+    pub(crate) prefix: &'a str,
+    pub(crate) delimiter: Option<&'a str>,
+    pub(crate) max_keys: Option<u16>,
+    pub(crate) continuation_token: Option<String>,
+    pub(crate) start_after: Option<String>,
+}
+
+pub struct ListPage {
+    pub(crate) entries: Vec<ObjectEntry>,
+    pub(crate) is_truncated: bool,
+    pub(crate) next_continuation_token: Option<String>,
+}
+
+// power 的存储后端，所有 key 都是存储中的实际 key(已经过改写)
+#[async_trait]
+pub trait Backend: Send + Sync {
+    // 读取对象，headers 中的 Range 和条件请求头会生效
+    async fn get(&self, bucket: &str, key: &str, headers: &HeaderMap) -> Result<ObjectResponse, ProxyError>;
+
+    async fn stat(&self, bucket: &str, key: &str) -> Result<ObjectMeta, ProxyError>;
+
+    async fn list_page(&self, bucket: &str, query: ListQuery<'_>) -> Result<ListPage, ProxyError>;
+
+    // 上传对象，返回 ETag；headers 为 Content-Type、x-amz-meta-* 等请求头
+    async fn put(&self, bucket: &str, key: &str, body: Bytes, headers: Multimap) -> Result<String, ProxyError>;
+
+    // 在同一个桶内复制对象
+    async fn copy(&self, bucket: &str, source: &str, target: &str) -> Result<(), ProxyError>;
+
+    async fn remove(&self, bucket: &str, key: &str) -> Result<(), ProxyError>;
+
+    // 列出 prefix 下的全部对象，自动翻页；delimiter 为 None 时递归列出
+    async fn list(&self, bucket: &str, prefix: &str, delimiter: Option<&str>) -> Result<Vec<ObjectEntry>, ProxyError> {
+        let mut entries = Vec::new();
+        let mut continuation_token = None;
+        loop {
+            let query = ListQuery { prefix, delimiter, continuation_token, ..Default::default() };
+            let page = self.list_page(bucket, query).await?;
+            entries.extend(page.entries);
+            match page.next_continuation_token {
+                Some(token) if page.is_truncated => continuation_token = Some(token),
+                _ => return Ok(entries),
+            }
+        }
+    }
+
+    // prefix 下是否存在对象，只读取一条
+    async fn prefix_exists(&self, bucket: &str, prefix: &str) -> Result<bool, ProxyError> {
+        let query = ListQuery { prefix, max_keys: Some(1), ..Default::default() };
+        Ok(!self.list_page(bucket, query).await?.entries.is_empty())
+    }
+}
+
+pub fn etag_matches(value: &str, etag: &str) -> bool {
+    let etag = etag.trim_start_matches("W/").trim_matches('"');
+    value.trim() == "*"
+        || value
+            .split(',')
+            .map(|tag| tag.trim().trim_start_matches("W/").trim_matches('"'))
+            .any(|tag| tag == etag)
+}
+
+// 按 If-None-Match、If-Modified-Since 判断是否返回 304，同时存在时只看 If-None-Match，时间按秒比较
+pub fn not_modified(headers: &HeaderMap, etag: &str, last_modified: Option<UtcTime>) -> bool {
+    let header = |name: &str| headers.get(name).and_then(|v| v.to_str().ok());
+    match header("if-none-match") {
+        Some(value) => etag_matches(value, etag),
+        None => match (header("if-modified-since").and_then(|v| from_http_header_value(v).ok()), last_modified) {
+            (Some(since), Some(modified)) => modified.timestamp() <= since.timestamp(),
+            _ => false,
+        },
+    }
+}
+
+// 从请求头或源对象的响应头中取出需要随对象保存的部分
+pub fn stored_headers(headers: &HeaderMap) -> Multimap {
+    let mut stored = Multimap::new();
+    for (name, value) in headers {
+        if STORED_HEADERS.contains(&name.as_str()) || name.as_str().starts_with("x-amz-meta-") {
+            if let Ok(value) = value.to_str() {
+                stored.insert(name.to_string(), value.to_string());
+            }
+        }
+    }
+    stored
+}
+
+// 按 power 的 backend 配置选择存储后端
+pub fn for_power(config_key: &str) -> Result<Box<dyn Backend>, ProxyError> {
+    for_config(&config::current(), config_key)
+}
+
+// 按给定的配置选择后端，调用方已经持有配置时使用
+pub fn for_config(config: &WarpConfig, config_key: &str) -> Result<Box<dyn Backend>, ProxyError> {
+    let power = match config.power_config(config_key) {
+        Some(power) if power.backend == BackendKind::Fs => power,
+        _ => return Ok(Box::new(minio_backend::MinioBackend::new(config_key))),
+    };
+    let root = power
+        .root
+        .clone()
+        .ok_or_else(|| ProxyError::Config(format!("Backend fs of {} requires a root", config_key)))?;
+    Ok(Box::new(fs_backend::FsBackend::new(root, power.bucket_mode == BucketMode::Path)))
+}
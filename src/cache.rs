@@ -1,84 +1,293 @@
 use std::collections::HashMap;
+use std::future::Future;
 use std::sync::{Arc, RwLock};
+use std::sync::atomic::{AtomicU64, Ordering};
 
 use lazy_static::lazy_static;
-use r2d2::Pool;
-use r2d2_redis::redis::{Commands, RedisResult};
-use r2d2_redis::RedisConnectionManager;
-use tokio::io::AsyncReadExt;
+use redis::aio::{ConnectionManager, MultiplexedConnection};
+use redis::cluster::ClusterClient;
+use redis::cluster_async::ClusterConnection;
+use redis::sentinel::{SentinelClient, SentinelNodeConnectionInfo, SentinelServerType};
+use redis::{AsyncCommands, Client, Cmd, ErrorKind, FromRedisValue, RedisConnectionInfo, RedisError, RedisResult, TlsMode};
+use serde::Serialize;
+use tokio::sync::Mutex;
 
-use crate::{cache, config};
-use crate::config::redis_config::RedisConfig;
-use crate::config::WARP_MINIO_CONFIG;
+use crate::config;
+use crate::config::redis_config::{RedisConfig, RedisMode};
 
 // 全局静态变量连接池
 lazy_static! {
-    static ref REDIS_POOLS: Arc<RwLock<HashMap<String, Pool<RedisConnectionManager>>>> = {
+    static ref REDIS_POOLS: Arc<RwLock<HashMap<String, Arc<RedisPool>>>> = {
         Arc::new(RwLock::new(HashMap::new()))
     };
 }
 
+enum RedisClient {
+    Standalone(Client),
+    Cluster(ClusterClient),
+    Sentinel(Mutex<SentinelClient>),
+}
+
+#[derive(Clone)]
+enum RedisConnection {
+    Standalone(ConnectionManager),
+    Cluster(ClusterConnection),
+    Sentinel(MultiplexedConnection),
+}
+
+// 一个 Redis 配置对应的异步客户端，连接在第一次使用时建立，出错后重新建立
+pub struct RedisPool {
+    config: RedisConfig,
+    client: RedisClient,
+    connection: Mutex<Option<RedisConnection>>,
+    commands: AtomicU64,
+    errors: AtomicU64,
+    connects: AtomicU64,
+}
+
+// 管理接口展示的连接统计
+#[derive(Serialize, Debug)]
+pub struct RedisPoolStats {
+    key: String,
+    mode: RedisMode,
+    connected: bool,
+    commands: u64,
+    errors: u64,
+    connects: u64,
+}
+
+impl RedisPool {
+    pub fn new(config: &RedisConfig) -> RedisResult<Self> {
+        let client = match config.mode {
+            RedisMode::Standalone => RedisClient::Standalone(Client::open(config.redis_url())?),
+            RedisMode::Cluster => RedisClient::Cluster(ClusterClient::new(config.node_urls())?),
+            RedisMode::Sentinel => {
+                let master_name = config.master_name.clone().ok_or_else(|| {
+                    RedisError::from((ErrorKind::InvalidClientConfig, "master-name is required in sentinel mode"))
+                })?;
+                let node_info = SentinelNodeConnectionInfo {
+                    tls_mode: config.tls.then_some(TlsMode::Secure),
+                    redis_connection_info: Some(RedisConnectionInfo {
+                        db: config.db.unwrap_or(0) as i64,
+                        username: config.username.clone(),
+                        password: config.password.as_ref().map(|password| password.expose().to_string()),
+                    }),
+                };
+                RedisClient::Sentinel(Mutex::new(SentinelClient::build(
+                    config.node_urls(),
+                    master_name,
+                    Some(node_info),
+                    SentinelServerType::Master,
+                )?))
+            }
+        };
+        Ok(RedisPool {
+            config: config.clone(),
+            client,
+            connection: Mutex::new(None),
+            commands: AtomicU64::new(0),
+            errors: AtomicU64::new(0),
+            connects: AtomicU64::new(0),
+        })
+    }
+
+    async fn connection(&self) -> RedisResult<RedisConnection> {
+        let mut connection = self.connection.lock().await;
+        if let Some(connection) = connection.as_ref() {
+            return Ok(connection.clone());
+        }
+
+        let created = with_timeout(self.config.timeout(), async {
+            match &self.client {
+                RedisClient::Standalone(client) => {
+                    ConnectionManager::new(client.clone()).await.map(RedisConnection::Standalone)
+                }
+                RedisClient::Cluster(client) => {
+                    client.get_async_connection().await.map(RedisConnection::Cluster)
+                }
+                RedisClient::Sentinel(client) => {
+                    client.lock().await.get_async_connection().await.map(RedisConnection::Sentinel)
+                }
+            }
+        }).await?;
+        self.connects.fetch_add(1, Ordering::Relaxed);
+        *connection = Some(created.clone());
+        Ok(created)
+    }
+
+    // 连接出错时丢弃缓存的连接，哨兵模式下会重新查询 master 地址
+    async fn reset(&self) {
+        *self.connection.lock().await = None;
+    }
+
+    async fn query<T, F, Fut>(&self, f: F) -> RedisResult<T>
+    where
+        F: FnOnce(RedisConnection) -> Fut,
+        Fut: Future<Output=RedisResult<T>>,
+    {
+        self.commands.fetch_add(1, Ordering::Relaxed);
+        let result = match self.connection().await {
+            Ok(connection) => with_timeout(self.config.timeout(), f(connection)).await,
+            Err(e) => Err(e),
+        };
+        if let Err(e) = &result {
+            self.errors.fetch_add(1, Ordering::Relaxed);
+            if e.is_io_error() || e.is_timeout() || e.is_connection_dropped() {
+                self.reset().await;
+            }
+        }
+        result
+    }
+
+    pub async fn get(&self, key: &str) -> RedisResult<Option<String>> {
+        self.query(|connection| async move {
+            match connection {
+                RedisConnection::Standalone(mut con) => con.get(key).await,
+                RedisConnection::Cluster(mut con) => con.get(key).await,
+                RedisConnection::Sentinel(mut con) => con.get(key).await,
+            }
+        }).await
+    }
+
+    pub async fn ping(&self) -> RedisResult<()> {
+        self.query(|connection| async move {
+            let cmd = redis::cmd("PING");
+            match connection {
+                RedisConnection::Standalone(mut con) => cmd.query_async(&mut con).await,
+                RedisConnection::Cluster(mut con) => cmd.query_async(&mut con).await,
+                RedisConnection::Sentinel(mut con) => cmd.query_async(&mut con).await,
+            }
+        }).await
+    }
+
+    // 执行任意命令，用于复制队列等需要多种命令的场景
+    pub async fn command<T: FromRedisValue>(&self, cmd: &Cmd) -> RedisResult<T> {
+        self.query(|connection| async move {
+            match connection {
+                RedisConnection::Standalone(mut con) => cmd.query_async(&mut con).await,
+                RedisConnection::Cluster(mut con) => cmd.query_async(&mut con).await,
+                RedisConnection::Sentinel(mut con) => cmd.query_async(&mut con).await,
+            }
+        }).await
+    }
+
+    pub async fn stats(&self) -> RedisPoolStats {
+        RedisPoolStats {
+            key: self.config.pool_key(),
+            mode: self.config.mode.clone(),
+            connected: self.connection.lock().await.is_some(),
+            commands: self.commands.load(Ordering::Relaxed),
+            errors: self.errors.load(Ordering::Relaxed),
+            connects: self.connects.load(Ordering::Relaxed),
+        }
+    }
+}
+
+async fn with_timeout<T, Fut>(timeout: std::time::Duration, f: Fut) -> RedisResult<T>
+where
+    Fut: Future<Output=RedisResult<T>>,
+{
+    match tokio::time::timeout(timeout, f).await {
+        Ok(result) => result,
+        Err(_) => Err(RedisError::from((ErrorKind::IoError, "Redis command timed out"))),
+    }
+}
+
+// 按当前配置重新创建全部 Redis 客户端，重新加载配置时同样调用
 pub fn initialize_redis_pools() {
+    let config = config::current();
+    let mut pools = HashMap::new();
 
     // default config
-    match &WARP_MINIO_CONFIG.default.redis_config {
+    match &config.default.redis_config {
         None => log::info!("Redis default config is None"),
         Some(vcr) => {
-            insert_pool(vcr);
+            insert_pool(&mut pools, vcr);
         }
     }
 
-    insert_pool(&WARP_MINIO_CONFIG.power_redis_configs());
+    insert_pool(&mut pools, &config.power_redis_configs());
 
+    *REDIS_POOLS.write().unwrap_or_else(|e| e.into_inner()) = pools;
     log::info!("Redis pool initialization completed");
 }
 
-fn insert_pool(config_redis: &Vec<RedisConfig>) {
-    let mut pools = REDIS_POOLS.write().unwrap();
-
+fn insert_pool(pools: &mut HashMap<String, Arc<RedisPool>>, config_redis: &Vec<RedisConfig>) {
     for config in config_redis {
-
-        let manager = match RedisConnectionManager::new(config.redis_url()) {
-            Ok(manager) => manager,
-            Err(_) => {
-                continue;
-            }
-        };
-
-        let pool = match Pool::builder()
-            .min_idle(config.idle_pool_size)
-            .max_size(config.max_pool_size.unwrap_or(8))
-            .build(manager) {
+        let pool = match RedisPool::new(config) {
             Ok(pool) => pool,
-            Err(_) => {
-                log::error!("Redis pool creation failed for key: {}", config.pool_key());
+            Err(e) => {
+                log::error!("Redis client creation failed for key: {}: {}", config.pool_key(), e);
                 continue;
             }
         };
-        pools.insert(format!("{}", config.pool_key()), pool);
+        pools.insert(config.pool_key(), Arc::new(pool));
+    }
+}
+
+// 依次 PING 全部 Redis 客户端，返回每个客户端的结果
+pub async fn ping_redis_pools() -> Vec<(String, Result<(), String>)> {
+    let pools: Vec<(String, Arc<RedisPool>)> = REDIS_POOLS
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .iter()
+        .map(|(key, pool)| (key.clone(), pool.clone()))
+        .collect();
+    let mut results = Vec::with_capacity(pools.len());
+    for (key, pool) in pools {
+        results.push((key, pool.ping().await.map_err(|e| e.to_string())));
+    }
+    results.sort_by(|a, b| a.0.cmp(&b.0));
+    results
+}
+
+pub async fn redis_pool_stats() -> Vec<RedisPoolStats> {
+    let pools: Vec<Arc<RedisPool>> = REDIS_POOLS
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .values()
+        .cloned()
+        .collect();
+    let mut stats = Vec::with_capacity(pools.len());
+    for pool in pools {
+        stats.push(pool.stats().await);
     }
+    stats.sort_by(|a, b| a.key.cmp(&b.key));
+    stats
 }
 
 
 // 获取minio配置
-pub fn get_minio_config(config_key: &str) -> RedisResult<Option<String>> {
-    let pool = match cache::get_redis_pool(config_key) {
+#[allow(dead_code)]
+pub async fn get_minio_config(config_key: &str) -> RedisResult<Option<String>> {
+    let pool = match get_redis_pool(config_key) {
         Ok(pool) => pool,
         Err(_) => return Ok(None)
     };
 
-    let mut con = pool
-        .get()
-        .expect("Failed to get connection from pool");
     let minio_config_key = format!("{}{}", config::MINIO_CONFIG_KEY_PREFIX, config_key);
-    con.get(&minio_config_key)
+    pool.get(&minio_config_key).await
 }
 
 
-pub fn get_redis_pool(key: &str) -> Result<Pool<RedisConnectionManager>, String> {
-    let redis = WARP_MINIO_CONFIG.get_redis_by_config_key(key);
+pub fn get_redis_pool(key: &str) -> Result<Arc<RedisPool>, String> {
+    let redis = config::current()
+        .get_redis_by_config_key(key)
+        .ok_or_else(|| format!("No Redis config found for key: {}", key))?;
+    pool_for(key, &redis)
+}
+
+// 固定使用 power 的第一个 Redis 配置，配置了多个 Redis 时不随机选择
+pub fn get_pinned_redis_pool(key: &str) -> Result<Arc<RedisPool>, String> {
+    let redis = config::current()
+        .pinned_redis_by_config_key(key)
+        .ok_or_else(|| format!("No Redis config found for key: {}", key))?;
+    pool_for(key, &redis)
+}
+
+fn pool_for(key: &str, redis: &RedisConfig) -> Result<Arc<RedisPool>, String> {
     let pools = REDIS_POOLS.read().map_err(|e| e.to_string())?;
-    pools.get(&redis.unwrap().pool_key())
+    pools.get(&redis.pool_key())
         .cloned()
         .ok_or_else(|| format!("No Redis pool found for key: {}", key))
-}
\ No newline at end of file
+}
@@ -0,0 +1,352 @@
+use std::path::Path;
+use std::time::Duration;
+
+use clap::{Parser, Subcommand};
+use minio::s3::args::{BucketExistsArgs, ListBucketsArgs};
+use r2d2::ManageConnection;
+use warp::http::{HeaderMap, HeaderValue};
+
+use crate::cache::RedisPool;
+use crate::config;
+use crate::config::loader::{self, IssueLevel};
+use crate::config::minio_config::MinioConfig;
+use crate::config::power_config::BackendKind;
+use crate::config::redis_config::{RedisConfig, RedisMode};
+use crate::config::warp_config::WarpConfig;
+use crate::minio::r2d2_minio::MinioConnectionManager;
+
+// 连通性检查的超时时间
+const CHECK_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Parser, Debug)]
+#[command(name = "warp_minio_server", version, about = "MinIO 请求转发服务")]
+pub struct Cli {
+    /// 配置文件路径，默认读取 WARP_MINIO_CONFIG_PATH 环境变量或 config.yaml
+    #[arg(short, long, global = true)]
+    pub config: Option<String>,
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// 启动转发服务(默认)
+    Serve,
+    /// 校验配置文件，并检查每个 power 的 Redis、MinIO 连通性
+    Check,
+    /// 输出解析后的配置，敏感字段会被隐藏
+    Dump,
+    /// 解析请求路径并生成预签名地址，验证对象是否可以访问
+    TestObject {
+        /// 请求路径，如 /minio/minio-atom/a/b.png 或 minio-atom/a/b.png
+        path: String,
+    },
+}
+
+// 需要检查的 power 名称、Redis 配置、MinIO 配置以及桶名
+type CheckTarget = (String, Vec<RedisConfig>, Vec<MinioConfig>, Vec<String>);
+
+// 一行检查结果
+struct CheckRow {
+    power: String,
+    kind: &'static str,
+    target: String,
+    ok: bool,
+    detail: String,
+}
+
+pub async fn check() -> i32 {
+    let config_path = config::config_path();
+    let config = match load(&config_path) {
+        Some(config) => config,
+        None => return 1,
+    };
+
+    let mut rows = Vec::new();
+    let mut targets: Vec<CheckTarget> = vec![(
+        "default".to_string(),
+        config.default.redis_config.clone().unwrap_or_default(),
+        config.default.minio_config.clone().unwrap_or_default(),
+        config.default.bucket_name.clone().into_iter().collect(),
+    )];
+    if let Some(power) = &config.power {
+        let mut keys: Vec<&String> = power.keys().collect();
+        keys.sort();
+        for key in keys {
+            let power_config = &power[key];
+            // bucket-name 与 buckets 中的桶都需要检查
+            let mut buckets: Vec<String> = power_config
+                .bucket_name
+                .clone()
+                .or_else(|| config.default.bucket_name.clone())
+                .into_iter()
+                .collect();
+            for bucket in power_config.buckets.iter().flatten() {
+                if !buckets.contains(bucket) {
+                    buckets.push(bucket.clone());
+                }
+            }
+            targets.push((
+                key.clone(),
+                power_config.redis_config.clone().unwrap_or_default(),
+                power_config.minio_config.clone().unwrap_or_default(),
+                buckets,
+            ));
+        }
+    }
+
+    // fs 后端只检查根目录
+    if let Some(power) = &config.power {
+        let mut fs_powers: Vec<(&String, &str)> = power
+            .iter()
+            .filter(|(_, power_config)| power_config.backend == BackendKind::Fs)
+            .map(|(key, power_config)| (key, power_config.root.as_deref().unwrap_or_default()))
+            .collect();
+        fs_powers.sort();
+        for (key, root) in fs_powers {
+            let ok = Path::new(root).is_dir();
+            rows.push(CheckRow {
+                power: key.clone(),
+                kind: "fs",
+                target: root.to_string(),
+                ok,
+                detail: if ok { "directory exists" } else { "directory not found" }.to_string(),
+            });
+        }
+    }
+
+    for (power, redis_configs, minio_configs, buckets) in targets {
+        for redis in &redis_configs {
+            let result = check_redis(redis).await;
+            rows.push(CheckRow {
+                power: power.clone(),
+                kind: "redis",
+                target: redis_target(redis),
+                ok: result.is_ok(),
+                detail: result.err().unwrap_or_else(|| "PONG".to_string()),
+            });
+        }
+        for minio in &minio_configs {
+            let result = check_minio(minio, &buckets).await;
+            rows.push(CheckRow {
+                power: power.clone(),
+                kind: "minio",
+                target: minio.endpoint.clone(),
+                ok: result.is_ok(),
+                detail: result.unwrap_or_else(|e| e),
+            });
+        }
+    }
+
+    print_table(&rows);
+    if rows.iter().all(|row| row.ok) { 0 } else { 1 }
+}
+
+pub fn dump() -> i32 {
+    let config = match load(&config::config_path()) {
+        Some(config) => config,
+        None => return 1,
+    };
+    // 密钥字段序列化时已隐藏
+    match serde_yaml::to_string(&config) {
+        Ok(yaml) => {
+            print!("{}", yaml);
+            0
+        }
+        Err(e) => {
+            eprintln!("Failed to serialize config: {}", e);
+            1
+        }
+    }
+}
+
+pub async fn test_object(path: &str) -> i32 {
+    let warp_config = config::current();
+    let url_prefix = warp_config
+        .match_prefix
+        .as_deref()
+        .unwrap_or(config::URL_PREFIX);
+    // 未带 match-prefix 的路径补上前缀，按请求的路由规则解析
+    let request_uri = match path.strip_prefix(url_prefix) {
+        Some(_) => path.to_string(),
+        None => format!("{}/{}", url_prefix.trim_end_matches('/'), path.trim_start_matches('/')),
+    };
+    let route = match crate::routing::resolve(&warp_config, None, &request_uri) {
+        Ok(route) => route,
+        Err(e) => {
+            eprintln!("{}: {}", e.code(), e);
+            return 1;
+        }
+    };
+    let (config_key, object_key) = (route.config_key.as_str(), route.object_key.as_str());
+    if config_key.is_empty() || object_key.is_empty() {
+        eprintln!("Path must contain a config key and an object key, e.g. minio-atom/a/b.png");
+        return 1;
+    }
+
+    if let Err(e) = crate::minio::minio_pool::initialize_minio_pools().await {
+        eprintln!("{}", e);
+        return 1;
+    }
+
+    let bucket = crate::minio::minio_parser::bucket_for(config_key, route.bucket.as_deref()).await;
+    println!("config key: {}", config_key);
+    println!("bucket:     {}", bucket);
+    println!("object:     {}", object_key);
+    let stored_key = crate::rewrite::rewrite_object_key(config_key, object_key);
+    if stored_key != object_key {
+        println!("rewritten:  {}", stored_key);
+    }
+
+    // fs 后端没有预签名地址
+    let backend_kind = warp_config.power_config(config_key).map(|power| power.backend.clone()).unwrap_or_default();
+    if backend_kind == BackendKind::Minio {
+        match crate::minio::minio_parser::get_generate_link_by_stored_key(config_key, Some(&bucket), &stored_key).await {
+            Ok(link) => println!("presigned:  {}", link),
+            Err(e) => {
+                println!("presign:    FAILED {} {}", e.code(), e);
+                return 1;
+            }
+        }
+    }
+
+    // 只读取第一个字节，验证对象可以访问
+    let mut headers = HeaderMap::new();
+    headers.insert("range", HeaderValue::from_static("bytes=0-0"));
+    let response = match crate::backend::for_power(config_key) {
+        Ok(backend) => tokio::time::timeout(CHECK_TIMEOUT, backend.get(&bucket, &stored_key, &headers)).await,
+        Err(e) => Ok(Err(e)),
+    };
+    match response {
+        Ok(Ok(response)) => {
+            let header = |name: &str| {
+                response
+                    .headers
+                    .get(name)
+                    .and_then(|v| v.to_str().ok())
+                    .unwrap_or("-")
+                    .to_string()
+            };
+            println!("status:     {}", response.status);
+            println!("type:       {}", header("Content-Type"));
+            println!("range:      {}", header("Content-Range"));
+            if response.status.is_success() { 0 } else { 1 }
+        }
+        Ok(Err(e)) => {
+            println!("fetch:      FAILED {} {}", e.code(), e);
+            1
+        }
+        Err(_) => {
+            println!("fetch:      FAILED timed out");
+            1
+        }
+    }
+}
+
+// 读取并校验配置，输出全部问题
+fn load(config_path: &str) -> Option<WarpConfig> {
+    match loader::load_config(Path::new(config_path)) {
+        Ok((config, warnings)) => {
+            for warning in warnings {
+                eprintln!("{}: {}", config_path, warning);
+            }
+            Some(config)
+        }
+        Err(issues) => {
+            for issue in &issues {
+                eprintln!("{}: {}", config_path, issue);
+            }
+            let errors = issues.iter().filter(|issue| issue.level == IssueLevel::Error).count();
+            eprintln!("Found {} error(s) in {}", errors, config_path);
+            None
+        }
+    }
+}
+
+// 检查结果中展示的 Redis 地址
+fn redis_target(config: &RedisConfig) -> String {
+    match config.mode {
+        RedisMode::Standalone => match config.port {
+            Some(port) => format!("{}:{}", config.host, port),
+            None => config.host.clone(),
+        },
+        _ => config.pool_key(),
+    }
+}
+
+async fn check_redis(config: &RedisConfig) -> Result<(), String> {
+    let pool = RedisPool::new(config).map_err(|e| e.to_string())?;
+    pool.ping().await.map_err(|e| e.to_string())
+}
+
+// 检查所有配置的桶；没有配置桶时调用 ListBuckets 验证地址和凭证
+async fn check_minio(config: &MinioConfig, buckets: &[String]) -> Result<String, String> {
+    let manager = MinioConnectionManager::new(config.clone());
+    let client = manager.connect().map_err(|e| e.to_string())?;
+    if buckets.is_empty() {
+        return match tokio::time::timeout(CHECK_TIMEOUT, client.list_buckets(&ListBucketsArgs::new())).await {
+            Ok(Ok(response)) => Ok(format!("no bucket configured, {} bucket(s) listed", response.buckets.len())),
+            Ok(Err(e)) => Err(e.to_string()),
+            Err(_) => Err("timed out".to_string()),
+        };
+    }
+
+    let mut failures = Vec::new();
+    for bucket in buckets {
+        let args = match BucketExistsArgs::new(bucket) {
+            Ok(args) => args,
+            Err(e) => {
+                failures.push(format!("bucket {}: {}", bucket, e));
+                continue;
+            }
+        };
+        match tokio::time::timeout(CHECK_TIMEOUT, client.bucket_exists(&args)).await {
+            Ok(Ok(true)) => {}
+            Ok(Ok(false)) => failures.push(format!("bucket {} not found", bucket)),
+            Ok(Err(e)) => failures.push(format!("bucket {}: {}", bucket, e)),
+            Err(_) => failures.push(format!("bucket {}: timed out", bucket)),
+        }
+    }
+    if !failures.is_empty() {
+        return Err(failures.join("; "));
+    }
+    match buckets {
+        [bucket] => Ok(format!("bucket {} exists", bucket)),
+        _ => Ok(format!("buckets {} exist", buckets.join(", "))),
+    }
+}
+
+fn print_table(rows: &[CheckRow]) {
+    let headers = ["POWER", "KIND", "TARGET", "STATUS", "DETAIL"];
+    let cells: Vec<[String; 5]> = rows
+        .iter()
+        .map(|row| [
+            row.power.clone(),
+            row.kind.to_string(),
+            row.target.clone(),
+            if row.ok { "OK" } else { "FAIL" }.to_string(),
+            row.detail.clone(),
+        ])
+        .collect();
+
+    let mut widths = headers.map(|h| h.len());
+    for row in &cells {
+        for (i, cell) in row.iter().enumerate() {
+            widths[i] = widths[i].max(cell.chars().count());
+        }
+    }
+
+    let format_row = |row: &[String]| {
+        row.iter()
+            .enumerate()
+            .map(|(i, cell)| format!("{:width$}", cell, width = widths[i]))
+            .collect::<Vec<_>>()
+            .join("  ")
+            .trim_end()
+            .to_string()
+    };
+    println!("{}", format_row(&headers.map(|h| h.to_string())));
+    for row in &cells {
+        println!("{}", format_row(row));
+    }
+}
@@ -0,0 +1,353 @@
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+
+use bytes::Bytes;
+use futures_util::StreamExt;
+use lazy_static::lazy_static;
+use tokio::sync::watch;
+use warp::http::{HeaderMap, StatusCode};
+
+use crate::backend::{ObjectResponse, CONDITIONAL_HEADERS};
+use crate::config;
+use crate::error::ProxyError;
+use crate::mirror;
+
+// 合并读取时在内存中保存的对象大小默认上限
+const DEFAULT_MAX_SIZE: u64 = 64 * 1024 * 1024;
+
+// 同一个对象的一次上游读取，响应头和已读到的数据块对所有等待的请求可见
+#[derive(Default)]
+struct Flight {
+    head: Option<Result<(StatusCode, HeaderMap), ProxyError>>,
+    // 对象大小未知或超过上限时不保存数据，上游响应体交给第一个取走的请求，其他请求各自读取
+    direct: Option<Arc<Mutex<Option<warp::hyper::Body>>>>,
+    chunks: Vec<Bytes>,
+    done: bool,
+    // 上游数据流中断，等待的请求也要中断而不是返回不完整的对象
+    failed: bool,
+}
+
+// (power, 路径中的桶, 对象 key, Range 和条件请求头)
+type FlightKey = (String, Option<String>, String, Vec<(&'static str, String)>);
+
+lazy_static! {
+    // 正在进行的读取，上游数据读完后移除，之后的请求重新读取
+    static ref FLIGHTS: Mutex<HashMap<FlightKey, watch::Receiver<Flight>>> = Mutex::new(HashMap::new());
+}
+
+// 读取对象，power 开启 coalesce 时相同对象、相同 Range 的并发请求只向上游读取一次
+pub async fn get(
+    config_key: &str,
+    route_bucket: Option<&str>,
+    object_key: &str,
+    headers: &HeaderMap,
+) -> Result<ObjectResponse, ProxyError> {
+    let max_size = match config::current().power_config(config_key).and_then(|power| power.coalesce.as_ref()) {
+        Some(coalesce) => coalesce.max_size.unwrap_or(DEFAULT_MAX_SIZE),
+        None => return mirror::get(config_key, route_bucket, object_key, headers).await,
+    };
+
+    let conditions: Vec<(&'static str, String)> = CONDITIONAL_HEADERS
+        .iter()
+        .filter_map(|name| Some((*name, headers.get(*name)?.to_str().ok()?.to_string())))
+        .collect();
+    let mut upstream_headers = HeaderMap::new();
+    for (name, value) in &conditions {
+        if let Ok(value) = value.parse() {
+            upstream_headers.insert(*name, value);
+        }
+    }
+    let key = (config_key.to_string(), route_bucket.map(str::to_string), object_key.to_string(), conditions);
+
+    let (config_key, route_bucket, object_key) = (key.0.clone(), key.1.clone(), key.2.clone());
+    let upstream = move || {
+        let (config_key, route_bucket, object_key, headers) =
+            (config_key.clone(), route_bucket.clone(), object_key.clone(), upstream_headers.clone());
+        async move { mirror::get(&config_key, route_bucket.as_deref(), &object_key, &headers).await }
+    };
+    coalesced(key, max_size, upstream).await
+}
+
+// 加入已有的读取或发起新的读取，upstream 每次调用向上游读取一次
+async fn coalesced<F, Fut>(key: FlightKey, max_size: u64, upstream: F) -> Result<ObjectResponse, ProxyError>
+where
+    F: Fn() -> Fut,
+    Fut: Future<Output = Result<ObjectResponse, ProxyError>> + Send + 'static,
+{
+    let mut receiver = {
+        let mut flights = FLIGHTS.lock().unwrap_or_else(|e| e.into_inner());
+        match flights.get(&key) {
+            Some(receiver) => {
+                log::debug!("Joined in-flight read of {} in {}", key.2, key.0);
+                receiver.clone()
+            }
+            None => {
+                let (sender, receiver) = watch::channel(Flight::default());
+                flights.insert(key.clone(), receiver.clone());
+                // 由后台任务读取上游，发起请求的客户端断开后其他请求不受影响
+                tokio::spawn(fetch(key, max_size, sender, upstream()));
+                receiver
+            }
+        }
+    };
+
+    let (head, direct) = {
+        let flight = receiver
+            .wait_for(|flight| flight.head.is_some())
+            .await
+            .map_err(|_| ProxyError::Upstream("Coalesced read was cancelled".to_string()))?;
+        (flight.head.clone(), flight.direct.clone())
+    };
+    let (status, headers) = head.unwrap_or_else(|| Err(ProxyError::Upstream("Coalesced read has no response".to_string())))?;
+
+    if let Some(direct) = direct {
+        let body = direct.lock().unwrap_or_else(|e| e.into_inner()).take();
+        return match body {
+            Some(body) => Ok(ObjectResponse { status, headers, body }),
+            None => upstream().await,
+        };
+    }
+
+    // 每个请求从第一个数据块开始读取，之后等待上游的新数据
+    let body = futures_util::stream::unfold(Some((receiver, 0usize)), |state| async move {
+        let (mut receiver, index) = state?;
+        loop {
+            let next = {
+                let flight = receiver.borrow_and_update();
+                match flight.chunks.get(index) {
+                    Some(chunk) => Some(Ok(chunk.clone())),
+                    None if flight.done && flight.failed => Some(Err(std::io::Error::other("Upstream body was interrupted"))),
+                    None if flight.done => return None,
+                    None => None,
+                }
+            };
+            match next {
+                Some(Ok(chunk)) => return Some((Ok(chunk), Some((receiver, index + 1)))),
+                Some(Err(e)) => return Some((Err(e), None)),
+                None => {
+                    if receiver.changed().await.is_err() {
+                        return Some((Err(std::io::Error::other("Coalesced read was cancelled")), None));
+                    }
+                }
+            }
+        }
+    });
+    Ok(ObjectResponse { status, headers, body: warp::hyper::Body::wrap_stream(body) })
+}
+
+async fn fetch<Fut>(key: FlightKey, max_size: u64, sender: watch::Sender<Flight>, upstream: Fut)
+where
+    Fut: Future<Output = Result<ObjectResponse, ProxyError>>,
+{
+    let (config_key, _, object_key, _) = &key;
+    match upstream.await {
+        Err(e) => sender.send_modify(|flight| {
+            flight.head = Some(Err(e));
+            flight.done = true;
+        }),
+        Ok(response) => {
+            let size = match response.status {
+                StatusCode::NOT_MODIFIED => Some(0),
+                _ => response
+                    .headers
+                    .get("content-length")
+                    .and_then(|value| value.to_str().ok())
+                    .and_then(|value| value.parse::<u64>().ok()),
+            };
+            if size.is_none_or(|size| size > max_size) {
+                log::debug!("Object {} in {} is not coalesced, size {:?} exceeds {}", object_key, config_key, size, max_size);
+                // 先移除再发送，之后的请求不会再加入这次读取
+                FLIGHTS.lock().unwrap_or_else(|e| e.into_inner()).remove(&key);
+                sender.send_modify(|flight| {
+                    flight.head = Some(Ok((response.status, response.headers)));
+                    flight.direct = Some(Arc::new(Mutex::new(Some(response.body))));
+                    flight.done = true;
+                });
+                return;
+            }
+
+            sender.send_modify(|flight| flight.head = Some(Ok((response.status, response.headers))));
+            let mut body = response.body;
+            while let Some(chunk) = body.next().await {
+                // 只剩 FLIGHTS 中的接收端时所有请求都已断开，停止读取；加锁避免与新加入的请求竞争
+                {
+                    let mut flights = FLIGHTS.lock().unwrap_or_else(|e| e.into_inner());
+                    if sender.receiver_count() <= 1 {
+                        flights.remove(&key);
+                        return;
+                    }
+                }
+                match chunk {
+                    Ok(chunk) => sender.send_modify(|flight| flight.chunks.push(chunk)),
+                    Err(e) => {
+                        log::warn!("Coalesced read of {} in {} failed: {}", object_key, config_key, e);
+                        sender.send_modify(|flight| flight.failed = true);
+                        break;
+                    }
+                }
+            }
+            sender.send_modify(|flight| flight.done = true);
+        }
+    }
+    FLIGHTS.lock().unwrap_or_else(|e| e.into_inner()).remove(&key);
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+
+    use warp::hyper::body::{to_bytes, Sender};
+
+    use super::*;
+
+    // 模拟的上游，每次读取返回一个由测试写入数据的响应体
+    #[derive(Clone)]
+    struct Upstream {
+        calls: Arc<AtomicUsize>,
+        senders: Arc<Mutex<Vec<Option<Sender>>>>,
+        content_length: Option<u64>,
+        // 返回响应头之前的等待时间，让多个请求在响应头到达前加入
+        delay: Duration,
+    }
+
+    impl Upstream {
+        fn new(content_length: Option<u64>, delay: Duration) -> Self {
+            Upstream { calls: Arc::default(), senders: Arc::default(), content_length, delay }
+        }
+
+        fn reader(&self) -> impl Fn() -> futures_util::future::BoxFuture<'static, Result<ObjectResponse, ProxyError>> {
+            let upstream = self.clone();
+            move || {
+                upstream.calls.fetch_add(1, Ordering::SeqCst);
+                let (sender, body) = warp::hyper::Body::channel();
+                upstream.senders.lock().unwrap().push(Some(sender));
+                let mut headers = HeaderMap::new();
+                if let Some(length) = upstream.content_length {
+                    headers.insert("content-length", length.into());
+                }
+                let delay = upstream.delay;
+                Box::pin(async move {
+                    tokio::time::sleep(delay).await;
+                    Ok(ObjectResponse { status: StatusCode::OK, headers, body })
+                })
+            }
+        }
+
+        fn calls(&self) -> usize {
+            self.calls.load(Ordering::SeqCst)
+        }
+
+        async fn sender(&self, index: usize) -> Sender {
+            loop {
+                if let Some(sender) = self.senders.lock().unwrap().get_mut(index).and_then(Option::take) {
+                    return sender;
+                }
+                tokio::time::sleep(Duration::from_millis(5)).await;
+            }
+        }
+    }
+
+    fn key(name: &str) -> FlightKey {
+        ("coalesce".to_string(), None, name.to_string(), Vec::new())
+    }
+
+    async fn wait_until(condition: impl Fn() -> bool) {
+        for _ in 0..200 {
+            if condition() {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+        panic!("condition was not met in time");
+    }
+
+    #[tokio::test]
+    async fn concurrent_requests_share_one_read() {
+        let upstream = Upstream::new(Some(4), Duration::ZERO);
+        let requests: Vec<_> = (0..3)
+            .map(|_| {
+                let read = upstream.reader();
+                tokio::spawn(async move { to_bytes(coalesced(key("join"), 1024, read).await.unwrap().body).await.unwrap() })
+            })
+            .collect();
+        // 数据写入之前读取不会结束，所有请求都会加入同一次读取
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        let mut sender = upstream.sender(0).await;
+        sender.send_data(Bytes::from("ab")).await.unwrap();
+        sender.send_data(Bytes::from("cd")).await.unwrap();
+        drop(sender);
+
+        for request in requests {
+            assert_eq!(request.await.unwrap(), "abcd");
+        }
+        assert_eq!(upstream.calls(), 1);
+    }
+
+    #[tokio::test]
+    async fn late_joiner_receives_whole_body() {
+        let upstream = Upstream::new(Some(4), Duration::ZERO);
+        let first = coalesced(key("late"), 1024, upstream.reader()).await.unwrap();
+        let mut first_body = first.body;
+        let mut sender = upstream.sender(0).await;
+        sender.send_data(Bytes::from("ab")).await.unwrap();
+        assert_eq!(first_body.next().await.unwrap().unwrap(), "ab");
+
+        // 已经读到部分数据后加入，从第一个数据块开始接收
+        let second = coalesced(key("late"), 1024, upstream.reader()).await.unwrap();
+        sender.send_data(Bytes::from("cd")).await.unwrap();
+        drop(sender);
+
+        assert_eq!(to_bytes(first_body).await.unwrap(), "cd");
+        assert_eq!(to_bytes(second.body).await.unwrap(), "abcd");
+        assert_eq!(upstream.calls(), 1);
+    }
+
+    #[tokio::test]
+    async fn read_stops_when_all_requests_disconnect() {
+        let upstream = Upstream::new(Some(4), Duration::ZERO);
+        let first = coalesced(key("gone"), 1024, upstream.reader()).await.unwrap();
+        let second = coalesced(key("gone"), 1024, upstream.reader()).await.unwrap();
+        assert_eq!(upstream.calls(), 1);
+        drop(first);
+        drop(second);
+
+        let mut sender = upstream.sender(0).await;
+        sender.send_data(Bytes::from("ab")).await.unwrap();
+        wait_until(|| !FLIGHTS.lock().unwrap().contains_key(&key("gone"))).await;
+        // 上游响应体已经释放，不再继续读取
+        assert!(sender.send_data(Bytes::from("cd")).await.is_err());
+
+        let third = coalesced(key("gone"), 1024, upstream.reader()).await.unwrap();
+        assert_eq!(upstream.calls(), 2);
+        let mut sender = upstream.sender(1).await;
+        sender.send_data(Bytes::from("abcd")).await.unwrap();
+        drop(sender);
+        assert_eq!(to_bytes(third.body).await.unwrap(), "abcd");
+    }
+
+    #[tokio::test]
+    async fn unknown_or_oversized_objects_are_read_separately() {
+        for (name, content_length) in [("unknown", None), ("oversized", Some(8))] {
+            let upstream = Upstream::new(content_length, Duration::from_millis(50));
+            let requests: Vec<_> = (0..2)
+                .map(|_| {
+                    let read = upstream.reader();
+                    tokio::spawn(async move { to_bytes(coalesced(key(name), 4, read).await.unwrap().body).await.unwrap() })
+                })
+                .collect();
+
+            // 第一次读取的响应体交给其中一个请求，另一个请求自己读取
+            for index in 0..2 {
+                let mut sender = upstream.sender(index).await;
+                sender.send_data(Bytes::from("abcdefgh")).await.unwrap();
+            }
+            for request in requests {
+                assert_eq!(request.await.unwrap(), "abcdefgh", "{}", name);
+            }
+            assert_eq!(upstream.calls(), 2, "{}", name);
+            assert!(!FLIGHTS.lock().unwrap().contains_key(&key(name)), "{}", name);
+        }
+    }
+}
@@ -0,0 +1,16 @@
+use serde::{Deserialize, Serialize};
+
+use crate::config::secret::Secret;
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct AdminConfig {
+    // 管理接口端口，必须与 server-port 不同
+    #[serde(rename = "port")]
+    pub(crate) port: u16,
+    // 监听地址，默认只监听本机
+    #[serde(rename = "bind")]
+    pub(crate) bind: Option<String>,
+    // 请求头 Authorization: Bearer <token>
+    #[serde(rename = "token")]
+    pub(crate) token: Secret,
+}
@@ -0,0 +1,8 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+pub struct CoalesceConfig {
+    // 合并读取时在内存中保存的对象大小上限(字节)，默认 64MiB，大小未知或超过时每个请求各自读取
+    #[serde(rename = "max-size")]
+    pub(crate) max_size: Option<u64>,
+}
@@ -1,9 +1,9 @@
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
 use crate::config::minio_config::MinioConfig;
 use crate::config::redis_config::RedisConfig;
 
-#[derive(Deserialize, Debug, Default)]
+#[derive(Deserialize, Serialize, Debug, Default)]
 pub struct DefaultConfig {
     #[serde(rename = "bucket-name")]
     pub(crate) bucket_name: Option<String>,
@@ -0,0 +1,20 @@
+use serde::{Deserialize, Serialize};
+
+// 错误时的兜底响应，object、file、template 按顺序取第一个配置的值
+#[derive(Deserialize, Serialize, Debug, Default, Clone)]
+pub struct FallbackConfig {
+    // 同一个桶中的对象，如 placeholders/missing.png
+    #[serde(rename = "object")]
+    pub(crate) object: Option<String>,
+    // 本地文件路径
+    #[serde(rename = "file")]
+    pub(crate) file: Option<String>,
+    // HTML 模板，支持 {status}、{code}、{message}、{request_id} 占位符
+    #[serde(rename = "template")]
+    pub(crate) template: Option<String>,
+    #[serde(rename = "content-type")]
+    pub(crate) content_type: Option<String>,
+    // 返回的状态码，默认沿用原始错误的状态码
+    #[serde(rename = "status")]
+    pub(crate) status: Option<u16>,
+}
@@ -0,0 +1,28 @@
+use serde::{Deserialize, Serialize};
+
+// MinIO 后端读取对象的方式
+#[derive(Deserialize, Serialize, Debug, Default, Clone, Copy, PartialEq)]
+pub enum FetchMode {
+    // 生成预签名地址，再由代理的 HTTP 客户端请求
+    #[default]
+    #[serde(rename = "presign")]
+    Presign,
+    // 使用连接池中的 MinIO 客户端直接 GET，与其他操作共用连接和凭证
+    #[serde(rename = "direct")]
+    Direct,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+pub struct FetchConfig {
+    #[serde(rename = "mode", default)]
+    pub(crate) mode: FetchMode,
+    // 发出请求到收到响应头的超时，包括建立连接，默认不限制
+    #[serde(rename = "header-timeout-ms")]
+    pub(crate) header_timeout_ms: Option<u64>,
+    // 两次收到数据之间的最长间隔，超过后中断响应，默认不限制
+    #[serde(rename = "read-timeout-ms")]
+    pub(crate) read_timeout_ms: Option<u64>,
+    // direct 模式下定期通过空闲的客户端访问上游，保持连接不被关闭，默认不发送
+    #[serde(rename = "keep-alive-ms")]
+    pub(crate) keep_alive_ms: Option<u64>,
+}
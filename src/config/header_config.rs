@@ -0,0 +1,38 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+// 响应头策略
+#[derive(Deserialize, Serialize, Debug, Default, Clone)]
+pub struct HeaderConfig {
+    // 按对象路径、Content-Type 设置 Cache-Control，取第一条匹配的规则
+    #[serde(rename = "cache-control")]
+    pub(crate) cache_control: Option<Vec<CacheControlRule>>,
+    // 是否添加 X-Content-Type-Options 以及 HTML/SVG 的 CSP 安全头
+    #[serde(rename = "security-headers", default)]
+    pub(crate) security_headers: bool,
+    // HTML/SVG 使用的 CSP，默认禁止脚本执行
+    #[serde(rename = "content-security-policy")]
+    pub(crate) content_security_policy: Option<String>,
+    // 需要去除的上游响应头，支持通配符，如 x-amz-*
+    #[serde(rename = "strip")]
+    pub(crate) strip: Option<Vec<String>>,
+    // 强制以附件形式下载的 Content-Type，支持通配符，防止存储型 XSS
+    #[serde(rename = "force-attachment")]
+    pub(crate) force_attachment: Option<Vec<String>>,
+    // 固定设置的响应头
+    #[serde(rename = "set")]
+    pub(crate) set: Option<HashMap<String, String>>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Default, Clone)]
+pub struct CacheControlRule {
+    // 对象路径通配符，如 *.png、static/**
+    #[serde(rename = "pattern")]
+    pub(crate) pattern: Option<String>,
+    // Content-Type 通配符，如 image/*
+    #[serde(rename = "content-type")]
+    pub(crate) content_type: Option<String>,
+    #[serde(rename = "value")]
+    pub(crate) value: String,
+}
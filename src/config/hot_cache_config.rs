@@ -0,0 +1,14 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+pub struct HotCacheConfig {
+    // 只缓存不超过该大小的对象(字节)，默认 256KiB
+    #[serde(rename = "max-object-size")]
+    pub(crate) max_object_size: Option<u64>,
+    // 该 power 缓存占用的内存上限(字节)，默认 64MiB，超过时淘汰最久未访问的对象；各 power 分别计算，没有全局上限
+    #[serde(rename = "max-memory")]
+    pub(crate) max_memory: Option<u64>,
+    // 缓存时间，默认 60000ms；上游 Cache-Control 的 max-age 更短时以 max-age 为准
+    #[serde(rename = "ttl-ms")]
+    pub(crate) ttl_ms: Option<u64>,
+}
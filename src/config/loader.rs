@@ -0,0 +1,1349 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::path::Path;
+
+use minio::s3::args::DEFAULT_EXPIRY_SECONDS;
+use serde::de::{DeserializeSeed, Deserializer, EnumAccess, IgnoredAny, MapAccess, SeqAccess, VariantAccess, Visitor};
+use serde_yaml::{Mapping, Value};
+
+use crate::auth::AuthType;
+use crate::rewrite;
+use crate::config::fetch_config::FetchMode;
+use crate::config::minio_config::{Addressing, MinioConfig};
+use crate::config::power_config::{BackendKind, BucketMode, PowerConfig};
+use crate::config::redis_config::RedisMode;
+use crate::config::rewrite_config::RewriteRule;
+use crate::config::warp_config::WarpConfig;
+use crate::minio::presign_cache::MIN_CACHED_EXPIRY_SECS;
+
+// 覆盖配置的环境变量前缀
+const ENV_OVERRIDE_PREFIX: &str = "WARP_MINIO__";
+
+// 从文件读取值的 key 后缀，如 secret-key-file
+const SECRET_FILE_SUFFIX: &str = "-file";
+
+// MinIO 客户端内部 HTTP 连接池关闭空闲连接的时间(reqwest 默认 90 秒)
+const UPSTREAM_IDLE_TIMEOUT_MS: u64 = 90_000;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum IssueLevel {
+    Warning,
+    Error,
+}
+
+// 配置文件中的一个问题，带有出错的配置路径和行号
+#[derive(Debug, Clone)]
+pub struct ConfigIssue {
+    pub(crate) level: IssueLevel,
+    pub(crate) path: String,
+    pub(crate) line: Option<usize>,
+    pub(crate) message: String,
+}
+
+impl fmt::Display for ConfigIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let level = match self.level {
+            IssueLevel::Warning => "warning",
+            IssueLevel::Error => "error",
+        };
+        match self.line {
+            Some(line) => write!(f, "line {}: {}: {}: {}", line, level, self.path, self.message),
+            None => write!(f, "{}: {}: {}", level, self.path, self.message),
+        }
+    }
+}
+
+// 配置路径中的一段，对应 YAML 的 key 或数组下标
+#[derive(Debug, Clone)]
+enum Segment {
+    Key(String),
+    Index(usize),
+}
+
+struct Validator<'a> {
+    content: &'a str,
+    issues: Vec<ConfigIssue>,
+}
+
+impl<'a> Validator<'a> {
+    fn push(&mut self, level: IssueLevel, path: &[Segment], message: String) {
+        self.issues.push(ConfigIssue {
+            level,
+            path: format_path(path),
+            line: locate(self.content, path),
+            message,
+        });
+    }
+
+    fn warn(&mut self, path: &[Segment], message: String) {
+        self.push(IssueLevel::Warning, path, message);
+    }
+
+    fn error(&mut self, path: &[Segment], message: String) {
+        self.push(IssueLevel::Error, path, message);
+    }
+}
+
+// 读取并校验配置文件，返回配置及警告；存在错误时返回全部问题
+pub fn load_config(path: &Path) -> Result<(WarpConfig, Vec<ConfigIssue>), Vec<ConfigIssue>> {
+    let file = path.display().to_string();
+    let content = match std::fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) => {
+            return Err(vec![ConfigIssue {
+                level: IssueLevel::Error,
+                path: file,
+                line: None,
+                message: format!("Failed to read config file: {}", e),
+            }]);
+        }
+    };
+    parse_config(&content)
+}
+
+pub fn parse_config(content: &str) -> Result<(WarpConfig, Vec<ConfigIssue>), Vec<ConfigIssue>> {
+    let mut validator = Validator { content, issues: Vec::new() };
+
+    // 先解析为 Value，语法错误和重复的 key 会在这里带行号报出
+    let mut value: Value = match serde_yaml::from_str(content) {
+        Ok(value) => value,
+        Err(e) => return Err(vec![yaml_issue(&e)]),
+    };
+
+    let plain = plain_placeholders(content);
+    interpolate_env(&mut validator, &mut value, &plain, &mut Vec::new());
+    apply_env_overrides(&mut validator, &mut value, std::env::vars());
+    resolve_secret_files(&mut validator, &mut value, &mut Vec::new());
+    normalize_redis_hosts(&mut validator, &mut value);
+
+    let mut unknown = Vec::new();
+    let config: Result<WarpConfig, _> = serde_ignored::deserialize(value, |path| {
+        unknown.push(ignored_path(&path));
+    });
+    let config = match config {
+        Ok(config) => config,
+        Err(e) => {
+            // 从原始文本重新解析以获得出错的行号；文本能解析时说明错误来自环境变量覆盖
+            let issue = match serde_yaml::from_str::<WarpConfig>(content) {
+                Err(e) => yaml_issue(&e),
+                Ok(_) => ConfigIssue {
+                    level: IssueLevel::Error,
+                    path: ".".to_string(),
+                    line: None,
+                    message: format!("Invalid value from environment: {}", e),
+                },
+            };
+            validator.issues.push(issue);
+            return Err(validator.issues);
+        }
+    };
+
+    for path in unknown {
+        let key = match path.last() {
+            Some(Segment::Key(key)) => key.clone(),
+            _ => String::new(),
+        };
+        validator.error(&path, format!("Unknown key `{}`", key));
+    }
+
+    validate_config(&mut validator, &config);
+
+    if validator.issues.iter().any(|issue| issue.level == IssueLevel::Error) {
+        return Err(validator.issues);
+    }
+    Ok((config, validator.issues))
+}
+
+// 替换字符串中的 ${VAR} 和 ${VAR:-default}；变量未设置时读取 VAR_FILE 指向的文件，$${ 表示字面量 ${
+// plain 为未加引号的标量路径，这些值按 YAML 标量解析，以支持端口、布尔值等；加引号时保持字符串
+fn interpolate_env(validator: &mut Validator, value: &mut Value, plain: &HashSet<String>, path: &mut Vec<Segment>) {
+    match value {
+        Value::String(text) if text.contains("${") => match interpolate(text) {
+            Ok(interpolated) => {
+                *value = match serde_yaml::from_str::<Value>(&interpolated) {
+                    Ok(parsed @ (Value::Number(_) | Value::Bool(_))) if plain.contains(&format_path(path)) => parsed,
+                    _ => Value::String(interpolated),
+                };
+            }
+            Err(message) => validator.error(path, message),
+        },
+        Value::Mapping(mapping) => {
+            for (k, v) in mapping.iter_mut() {
+                path.push(key(k.as_str().unwrap_or_default()));
+                interpolate_env(validator, v, plain, path);
+                path.pop();
+            }
+        }
+        Value::Sequence(sequence) => {
+            for (index, v) in sequence.iter_mut().enumerate() {
+                path.push(Segment::Index(index));
+                interpolate_env(validator, v, plain, path);
+                path.pop();
+            }
+        }
+        Value::Tagged(tagged) => interpolate_env(validator, &mut tagged.value, plain, path),
+        _ => {}
+    }
+}
+
+// Value 不保留标量的引号样式，这里用解析器再遍历一次原文：单行标量会以原文切片的形式返回，
+// 切片前一个字符是引号即为加了引号的标量。多行、含转义等无法借用原文的标量按加了引号处理
+fn plain_placeholders(content: &str) -> HashSet<String> {
+    let mut path = Vec::new();
+    let mut found = HashSet::new();
+    let seed = PlainScalars { content, path: &mut path, found: &mut found };
+    match seed.deserialize(serde_yaml::Deserializer::from_str(content)) {
+        Ok(()) => found,
+        Err(_) => HashSet::new(),
+    }
+}
+
+struct PlainScalars<'a, 'de> {
+    content: &'de str,
+    path: &'a mut Vec<Segment>,
+    found: &'a mut HashSet<String>,
+}
+
+impl<'a, 'de> PlainScalars<'a, 'de> {
+    fn child(&mut self) -> PlainScalars<'_, 'de> {
+        PlainScalars { content: self.content, path: self.path, found: self.found }
+    }
+}
+
+impl<'a, 'de> DeserializeSeed<'de> for PlainScalars<'a, 'de> {
+    type Value = ();
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+        deserializer.deserialize_any(self)
+    }
+}
+
+impl<'a, 'de> Visitor<'de> for PlainScalars<'a, 'de> {
+    type Value = ();
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("any YAML value")
+    }
+
+    fn visit_borrowed_str<E>(self, v: &'de str) -> Result<(), E> {
+        if !v.contains("${") {
+            return Ok(());
+        }
+        let offset = (v.as_ptr() as usize).checked_sub(self.content.as_ptr() as usize);
+        let quoted = match offset.and_then(|offset| self.content.as_bytes().get(..offset)) {
+            Some(before) => matches!(before.last(), Some(b'"' | b'\'')),
+            None => true,
+        };
+        if !quoted {
+            self.found.insert(format_path(self.path));
+        }
+        Ok(())
+    }
+
+    fn visit_str<E>(self, _: &str) -> Result<(), E> {
+        Ok(())
+    }
+
+    fn visit_bool<E>(self, _: bool) -> Result<(), E> {
+        Ok(())
+    }
+
+    fn visit_i64<E>(self, _: i64) -> Result<(), E> {
+        Ok(())
+    }
+
+    fn visit_u64<E>(self, _: u64) -> Result<(), E> {
+        Ok(())
+    }
+
+    fn visit_i128<E>(self, _: i128) -> Result<(), E> {
+        Ok(())
+    }
+
+    fn visit_u128<E>(self, _: u128) -> Result<(), E> {
+        Ok(())
+    }
+
+    fn visit_f64<E>(self, _: f64) -> Result<(), E> {
+        Ok(())
+    }
+
+    fn visit_unit<E>(self) -> Result<(), E> {
+        Ok(())
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(mut self, mut seq: A) -> Result<(), A::Error> {
+        let mut index = 0;
+        loop {
+            self.path.push(Segment::Index(index));
+            let next = seq.next_element_seed(self.child());
+            self.path.pop();
+            if next?.is_none() {
+                return Ok(());
+            }
+            index += 1;
+        }
+    }
+
+    fn visit_map<A: MapAccess<'de>>(mut self, mut map: A) -> Result<(), A::Error> {
+        while let Some(k) = map.next_key::<Value>()? {
+            self.path.push(key(k.as_str().unwrap_or_default()));
+            let next = map.next_value_seed(self.child());
+            self.path.pop();
+            next?;
+        }
+        Ok(())
+    }
+
+    fn visit_enum<A: EnumAccess<'de>>(self, data: A) -> Result<(), A::Error> {
+        // 带 !tag 的值，与 Value::Tagged 一样沿用同一路径
+        let (_, variant) = data.variant::<IgnoredAny>()?;
+        variant.newtype_variant_seed(self)
+    }
+}
+
+fn interpolate(text: &str) -> Result<String, String> {
+    let mut result = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('$') {
+        result.push_str(&rest[..start]);
+        let after = &rest[start..];
+        if let Some(escaped) = after.strip_prefix("$${") {
+            result.push_str("${");
+            rest = escaped;
+            continue;
+        }
+        let Some(body) = after.strip_prefix("${") else {
+            result.push('$');
+            rest = &after[1..];
+            continue;
+        };
+        let end = body.find('}').ok_or_else(|| format!("Unclosed `${{` in `{}`", text))?;
+        let expr = &body[..end];
+        let (name, default) = match expr.split_once(":-") {
+            Some((name, default)) => (name, Some(default)),
+            None => (expr, None),
+        };
+        if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+            return Err(format!("Invalid environment variable name `{}`", name));
+        }
+        match env_value(name)? {
+            Some(value) => result.push_str(&value),
+            None => match default {
+                Some(default) => result.push_str(default),
+                None => return Err(format!("Environment variable `{}` is not set", name)),
+            },
+        }
+        rest = &body[end + 1..];
+    }
+    result.push_str(rest);
+    Ok(result)
+}
+
+fn env_value(name: &str) -> Result<Option<String>, String> {
+    if let Ok(value) = std::env::var(name) {
+        return Ok(Some(value));
+    }
+    let file_var = format!("{}_FILE", name);
+    match std::env::var(&file_var) {
+        Ok(file) => read_secret_file(&file).map(Some).map_err(|e| format!("{} ({})", e, file_var)),
+        Err(_) => Ok(None),
+    }
+}
+
+// 密钥文件通常以换行结尾(如 Kubernetes Secret)，读取时去掉末尾的换行
+fn read_secret_file(file: &str) -> Result<String, String> {
+    std::fs::read_to_string(file)
+        .map(|content| content.trim_end_matches(['\r', '\n']).to_string())
+        .map_err(|e| format!("Failed to read secret file `{}`: {}", file, e))
+}
+
+// 以 WARP_MINIO__ 开头的环境变量覆盖配置，`__` 分隔层级，如
+// WARP_MINIO__POWER__minio-atom__BUCKET_NAME=atom、WARP_MINIO__DEFAULT__MINIO_CONFIG__0__SECRET_KEY=xxx
+fn apply_env_overrides(validator: &mut Validator, value: &mut Value, vars: impl Iterator<Item=(String, String)>) {
+    let mut overrides: Vec<(String, String)> = vars
+        .filter(|(name, _)| name.starts_with(ENV_OVERRIDE_PREFIX))
+        .collect();
+    // 按名称排序，保证同一层级的覆盖顺序稳定
+    overrides.sort();
+
+    for (name, raw) in overrides {
+        let segments: Vec<&str> = name[ENV_OVERRIDE_PREFIX.len()..].split("__").collect();
+        if segments.iter().any(|segment| segment.is_empty()) {
+            validator.error(&[], format!("Invalid config override `{}`", name));
+            continue;
+        }
+        if let Err(message) = apply_env_override(value, &segments, &raw) {
+            validator.error(&[], format!("{}: {}", name, message));
+        }
+    }
+}
+
+fn apply_env_override(value: &mut Value, segments: &[&str], raw: &str) -> Result<(), String> {
+    let Some((segment, rest)) = segments.split_first() else {
+        // 原值为字符串时保持字符串，其余按 YAML 标量解析，以支持端口、布尔值等
+        *value = match value {
+            Value::String(_) => Value::String(raw.to_string()),
+            _ => serde_yaml::from_str::<Value>(raw)
+                .ok()
+                .filter(|parsed| !matches!(parsed, Value::Mapping(_) | Value::Sequence(_) | Value::Null))
+                .unwrap_or_else(|| Value::String(raw.to_string())),
+        };
+        return Ok(());
+    };
+
+    if value.is_null() {
+        *value = match segment.parse::<usize>() {
+            Ok(_) => Value::Sequence(Vec::new()),
+            Err(_) => Value::Mapping(Mapping::new()),
+        };
+    }
+    match value {
+        Value::Mapping(mapping) => {
+            let existing = mapping
+                .keys()
+                .filter_map(|k| k.as_str())
+                .find(|k| *k == *segment)
+                .or_else(|| mapping.keys().filter_map(|k| k.as_str()).find(|k| env_key_matches(k, segment)))
+                .map(str::to_string);
+            let name = existing.unwrap_or_else(|| segment.to_lowercase().replace('_', "-"));
+            let child = mapping.entry(Value::String(name)).or_insert(Value::Null);
+            apply_env_override(child, rest, raw)
+        }
+        Value::Sequence(sequence) => {
+            let index: usize = segment
+                .parse()
+                .map_err(|_| format!("`{}` is not an index of a list", segment))?;
+            if index > sequence.len() {
+                return Err(format!("Index {} is out of range, list has {} item(s)", index, sequence.len()));
+            }
+            if index == sequence.len() {
+                sequence.push(Value::Null);
+            }
+            apply_env_override(&mut sequence[index], rest, raw)
+        }
+        Value::Tagged(tagged) => apply_env_override(&mut tagged.value, segments, raw),
+        _ => Err(format!("Cannot override `{}` inside a scalar value", segment)),
+    }
+}
+
+// 环境变量名不区分大小写，`_` 与 `-` 视为相同
+fn env_key_matches(key: &str, segment: &str) -> bool {
+    key.len() == segment.len()
+        && key
+            .chars()
+            .zip(segment.chars())
+            .all(|(a, b)| a.eq_ignore_ascii_case(&b) || (matches!(a, '-' | '_') && matches!(b, '-' | '_')))
+}
+
+// `xxx-file: /path` 从文件读取 `xxx` 的值，用于挂载的密钥文件
+fn resolve_secret_files(validator: &mut Validator, value: &mut Value, path: &mut Vec<Segment>) {
+    match value {
+        Value::Mapping(mapping) => {
+            let file_keys: Vec<String> = mapping
+                .keys()
+                .filter_map(|k| k.as_str())
+                .filter(|k| k.len() > SECRET_FILE_SUFFIX.len() && k.ends_with(SECRET_FILE_SUFFIX))
+                .map(str::to_string)
+                .collect();
+            for file_key in file_keys {
+                let target = file_key[..file_key.len() - SECRET_FILE_SUFFIX.len()].to_string();
+                path.push(key(&file_key));
+                let file = mapping.remove(file_key.as_str());
+                match file.as_ref().and_then(|f| f.as_str()) {
+                    None => validator.error(path, "Secret file path must be a string".to_string()),
+                    Some(_) if mapping.contains_key(target.as_str()) => {
+                        validator.error(path, format!("Both `{}` and `{}` are set", target, file_key))
+                    }
+                    Some(file) => match read_secret_file(file) {
+                        Ok(secret) => {
+                            mapping.insert(Value::String(target), Value::String(secret));
+                        }
+                        Err(message) => validator.error(path, message),
+                    },
+                }
+                path.pop();
+            }
+            for (k, v) in mapping.iter_mut() {
+                path.push(key(k.as_str().unwrap_or_default()));
+                resolve_secret_files(validator, v, path);
+                path.pop();
+            }
+        }
+        Value::Sequence(sequence) => {
+            for (index, v) in sequence.iter_mut().enumerate() {
+                path.push(Segment::Index(index));
+                resolve_secret_files(validator, v, path);
+                path.pop();
+            }
+        }
+        Value::Tagged(tagged) => resolve_secret_files(validator, &mut tagged.value, path),
+        _ => {}
+    }
+}
+
+fn yaml_issue(e: &serde_yaml::Error) -> ConfigIssue {
+    ConfigIssue {
+        level: IssueLevel::Error,
+        path: ".".to_string(),
+        line: e.location().map(|location| location.line()),
+        message: e.to_string(),
+    }
+}
+
+// 去除 Redis host 中误写的 http:// 等前缀，rediss:// 会保留用于开启 TLS
+fn normalize_redis_hosts(validator: &mut Validator, value: &mut Value) {
+    let mut targets: Vec<Vec<Segment>> = Vec::new();
+    if value.get("default").is_some() {
+        targets.push(vec![key("default"), key("redis-config")]);
+    }
+    if let Some(Value::Mapping(power)) = value.get("power") {
+        for power_key in power.keys().filter_map(|k| k.as_str()) {
+            targets.push(vec![key("power"), key(power_key), key("redis-config")]);
+        }
+    }
+
+    for target in targets {
+        let Some(Value::Sequence(configs)) = get_mut(value, &target) else {
+            continue;
+        };
+        let mut changes = Vec::new();
+        for (index, config) in configs.iter_mut().enumerate() {
+            let Value::Mapping(config) = config else { continue };
+            if let Some(change) = normalize_host_field(config, "host") {
+                changes.push((index, "host".to_string(), change));
+            }
+            if let Some(Value::Sequence(nodes)) = config.get_mut("nodes") {
+                for (node_index, node) in nodes.iter_mut().enumerate() {
+                    if let Some(change) = normalize_host_value(node) {
+                        changes.push((index, format!("nodes.{}", node_index), change));
+                    }
+                }
+            }
+        }
+        for (index, field, (from, to)) in changes {
+            let mut path = target.clone();
+            path.push(Segment::Index(index));
+            for part in field.split('.') {
+                path.push(match part.parse::<usize>() {
+                    Ok(i) => Segment::Index(i),
+                    Err(_) => key(part),
+                });
+            }
+            validator.warn(&path, format!("Redis host `{}` normalized to `{}`", from, to));
+        }
+    }
+}
+
+fn normalize_host_field(config: &mut Mapping, field: &str) -> Option<(String, String)> {
+    normalize_host_value(config.get_mut(field)?)
+}
+
+fn normalize_host_value(value: &mut Value) -> Option<(String, String)> {
+    let host = value.as_str()?.trim().to_string();
+    let normalized = normalize_redis_host(&host);
+    if normalized == host {
+        return None;
+    }
+    *value = Value::String(normalized.clone());
+    Some((host, normalized))
+}
+
+pub fn normalize_redis_host(host: &str) -> String {
+    let host = host.trim();
+    for prefix in ["https://", "http://", "https:", "http:"] {
+        if let Some(stripped) = host.strip_prefix(prefix) {
+            return stripped.trim_start_matches('/').trim_end_matches('/').to_string();
+        }
+    }
+    host.trim_end_matches('/').to_string()
+}
+
+fn validate_config(validator: &mut Validator, config: &WarpConfig) {
+    if config.server_port == Some(0) {
+        validator.warn(&[key("server-port")], "Port 0 is ignored, default port will be used".to_string());
+    }
+
+    if let Some(prefix) = &config.match_prefix {
+        if !prefix.starts_with('/') {
+            validator.error(&[key("match-prefix")], format!("`{}` must start with `/`", prefix));
+        }
+    }
+
+    let default_redis = config.default.redis_config.as_deref().unwrap_or_default();
+    validate_redis_configs(validator, &[key("default"), key("redis-config")], default_redis);
+    validate_minio_configs(
+        validator,
+        &[key("default"), key("minio-config")],
+        config.default.minio_config.as_deref().unwrap_or_default(),
+    );
+
+    let mut has_redis = !default_redis.is_empty();
+    // 域名 -> 第一个声明它的 power
+    let mut hosts: HashMap<String, &str> = HashMap::new();
+    if let Some(power) = &config.power {
+        let mut power_keys: Vec<&String> = power.keys().collect();
+        power_keys.sort();
+        for power_key in power_keys {
+            let power_config = &power[power_key];
+            let base = [key("power"), key(power_key)];
+            for (index, rule) in power_config.rewrite.iter().flatten().enumerate() {
+                let path = [base[0].clone(), base[1].clone(), key("rewrite"), Segment::Index(index)];
+                validate_rewrite_rule(validator, &path, rule);
+            }
+            for (index, host) in power_config.hosts.iter().flatten().enumerate() {
+                let path = [base[0].clone(), base[1].clone(), key("hosts"), Segment::Index(index)];
+                if host.is_empty() || host.contains(['/', ':', ' ']) {
+                    validator.error(&path, format!("Invalid host `{}`, use a bare domain like files.a.com or *.b.com", host));
+                } else if let Some(other) = hosts.insert(host.to_ascii_lowercase(), power_key) {
+                    validator.error(&path, format!("Host `{}` is already used by power `{}`", host, other));
+                }
+            }
+            let redis = power_config.redis_config.as_deref().unwrap_or_default();
+            has_redis |= !redis.is_empty();
+            validate_redis_configs(validator, &[base[0].clone(), base[1].clone(), key("redis-config")], redis);
+
+            match (&power_config.backend, power_config.minio_config.as_deref()) {
+                (BackendKind::Fs, minio) => {
+                    match &power_config.root {
+                        None => validator.error(&with(&base, "root"), "backend `fs` requires a root directory".to_string()),
+                        Some(root) if !Path::new(root).is_dir() => {
+                            validator.warn(&with(&base, "root"), format!("Directory `{}` does not exist", root))
+                        }
+                        Some(_) => {}
+                    }
+                    if minio.is_some_and(|minio| !minio.is_empty()) {
+                        validator.warn(&with(&base, "minio-config"), "minio-config is ignored by backend `fs`".to_string());
+                    }
+                }
+                (BackendKind::Minio, None | Some([])) => validator.warn(&base, "No minio-config, requests to this power will fail".to_string()),
+                (BackendKind::Minio, Some(minio)) => validate_minio_configs(validator, &[base[0].clone(), base[1].clone(), key("minio-config")], minio),
+            }
+
+            if let Some(webdav) = &power_config.webdav {
+                if webdav.max_put_size == Some(0) {
+                    validator.error(&[base[0].clone(), base[1].clone(), key("webdav"), key("max-put-size")], "max-put-size must be greater than 0".to_string());
+                }
+                if webdav.writable && matches!(config.auth_type, None | Some(AuthType::None)) {
+                    validator.warn(&with(&base, "webdav"), "WebDAV is writable without auth-type, anyone can modify the bucket".to_string());
+                }
+            }
+
+            if let Some(mirror) = &power_config.mirror {
+                let mirror_path = with(&base, "mirror");
+                validate_power_refs(validator, &with(&mirror_path, "sources"), power_key, &mirror.sources, power);
+                if mirror.max_copy_size == Some(0) {
+                    validator.error(&with(&mirror_path, "max-copy-size"), "max-copy-size must be greater than 0".to_string());
+                }
+            }
+
+            if power_config.coalesce.as_ref().is_some_and(|coalesce| coalesce.max_size == Some(0)) {
+                validator.error(&with(&with(&base, "coalesce"), "max-size"), "max-size must be greater than 0".to_string());
+            }
+
+            if let Some(upload) = &power_config.upload {
+                if upload.max_put_size == Some(0) {
+                    validator.error(&[base[0].clone(), base[1].clone(), key("upload"), key("max-put-size")], "max-put-size must be greater than 0".to_string());
+                }
+                if matches!(config.auth_type, None | Some(AuthType::None)) {
+                    validator.warn(&with(&base, "upload"), "Uploads are enabled without auth-type, anyone can write objects".to_string());
+                }
+            }
+
+            if let Some(expiry) = power_config.presign.as_ref().and_then(|presign| presign.expiry_secs) {
+                if expiry == 0 || expiry > DEFAULT_EXPIRY_SECONDS {
+                    validator.error(
+                        &[base[0].clone(), base[1].clone(), key("presign"), key("expiry-secs")],
+                        format!("expiry-secs must be between 1 and {}", DEFAULT_EXPIRY_SECONDS),
+                    );
+                } else if u64::from(expiry) < MIN_CACHED_EXPIRY_SECS && power_config.presign.as_ref().is_some_and(|presign| presign.cache != Some(false)) {
+                    validator.warn(
+                        &[base[0].clone(), base[1].clone(), key("presign"), key("expiry-secs")],
+                        "Presigned URLs expiring in less than 10 seconds are not cached".to_string(),
+                    );
+                }
+            }
+
+            if let Some(fetch) = &power_config.fetch {
+                let fetch_path = with(&base, "fetch");
+                for (name, value) in [
+                    ("header-timeout-ms", fetch.header_timeout_ms),
+                    ("read-timeout-ms", fetch.read_timeout_ms),
+                    ("keep-alive-ms", fetch.keep_alive_ms),
+                ] {
+                    if value == Some(0) {
+                        validator.error(&with(&fetch_path, name), format!("{} must be greater than 0", name));
+                    }
+                }
+                if power_config.backend == BackendKind::Fs {
+                    validator.warn(&fetch_path, "fetch is ignored by backend `fs`".to_string());
+                }
+                match fetch.keep_alive_ms {
+                    Some(_) if fetch.mode == FetchMode::Presign => {
+                        validator.warn(&with(&fetch_path, "keep-alive-ms"), "keep-alive-ms only applies to mode `direct`".to_string())
+                    }
+                    Some(ms) if ms >= UPSTREAM_IDLE_TIMEOUT_MS => validator.warn(
+                        &with(&fetch_path, "keep-alive-ms"),
+                        format!("Idle upstream connections are closed after {}ms, keep-alive-ms {} is too long", UPSTREAM_IDLE_TIMEOUT_MS, ms),
+                    ),
+                    // 保活请求访问 power 的桶
+                    Some(_) if config.bucket_name(power_key.clone()).or_else(|| config.default.bucket_name.clone()).is_none() => validator.warn(
+                        &with(&fetch_path, "keep-alive-ms"),
+                        "keep-alive-ms requires bucket-name, buckets or a default bucket-name".to_string(),
+                    ),
+                    _ => {}
+                }
+            }
+
+            if let Some(hot_cache) = &power_config.hot_cache {
+                let hot_cache_path = with(&base, "hot-cache");
+                for (name, value) in [
+                    ("max-object-size", hot_cache.max_object_size),
+                    ("max-memory", hot_cache.max_memory),
+                    ("ttl-ms", hot_cache.ttl_ms),
+                ] {
+                    if value == Some(0) {
+                        validator.error(&with(&hot_cache_path, name), format!("{} must be greater than 0", name));
+                    }
+                }
+                if let (Some(object), Some(memory)) = (hot_cache.max_object_size, hot_cache.max_memory) {
+                    if object > memory {
+                        validator.warn(&with(&hot_cache_path, "max-object-size"), format!("max-object-size {} is larger than max-memory {}", object, memory));
+                    }
+                }
+            }
+
+            if let Some(replication) = &power_config.replication {
+                let replication_path = with(&base, "replication");
+                validate_power_refs(validator, &with(&replication_path, "targets"), power_key, &replication.targets, power);
+                if power_config.upload.is_none() {
+                    validator.warn(&replication_path, "Only uploads through the proxy are replicated, but upload is not enabled".to_string());
+                }
+                if power_config.redis_config.as_deref().unwrap_or_default().is_empty() && default_redis.is_empty() {
+                    validator.error(&replication_path, "Replication requires a redis-config in the power or default".to_string());
+                }
+                if replication.max_attempts == Some(0) {
+                    validator.error(&with(&replication_path, "max-attempts"), "max-attempts must be greater than 0".to_string());
+                }
+                if replication.retry_interval_ms == Some(0) {
+                    validator.error(&with(&replication_path, "retry-interval-ms"), "retry-interval-ms must be greater than 0".to_string());
+                }
+            }
+
+            let buckets = power_config.buckets.as_deref().unwrap_or_default();
+            match power_config.bucket_mode {
+                BucketMode::Path => {
+                    if buckets.is_empty() {
+                        validator.error(&with(&base, "buckets"), "bucket-mode `path` requires a non-empty buckets list".to_string());
+                    }
+                }
+                BucketMode::Fixed => {
+                    if let Some(bucket_name) = &power_config.bucket_name {
+                        if !buckets.is_empty() && !buckets.contains(bucket_name) {
+                            validator.warn(&with(&base, "bucket-name"), format!("Bucket `{}` is not in buckets", bucket_name));
+                        }
+                    }
+                    // fs 后端在 fixed 模式下根目录即为桶
+                    if power_config.backend == BackendKind::Minio
+                        && power_config.bucket_name.is_none()
+                        && buckets.is_empty()
+                        && config.default.bucket_name.is_none()
+                    {
+                        validator.warn(&base, "No bucket-name in power or default".to_string());
+                    }
+                }
+            }
+        }
+    }
+
+    if let Some(admin) = &config.admin {
+        let server_port = config.server_port.filter(|port| *port != 0).unwrap_or(super::PORT);
+        if admin.port == 0 || admin.port == server_port {
+            validator.error(&[key("admin"), key("port")], format!("Admin port {} must be non-zero and differ from server-port", admin.port));
+        }
+        if admin.token.expose().len() < 16 {
+            validator.error(&[key("admin"), key("token")], "Admin token must be at least 16 characters".to_string());
+        }
+        if let Some(bind) = &admin.bind {
+            if bind.parse::<std::net::IpAddr>().is_err() {
+                validator.error(&[key("admin"), key("bind")], format!("Invalid bind address `{}`", bind));
+            }
+        }
+    }
+
+    if let Some(s3) = &config.s3 {
+        let server_port = config.server_port.filter(|port| *port != 0).unwrap_or(super::PORT);
+        let admin_port = config.admin.as_ref().map(|admin| admin.port);
+        if s3.port == 0 || s3.port == server_port || Some(s3.port) == admin_port {
+            validator.error(&[key("s3"), key("port")], format!("S3 port {} must be non-zero and differ from server-port and admin.port", s3.port));
+        }
+        if let Some(bind) = &s3.bind {
+            if bind.parse::<std::net::IpAddr>().is_err() {
+                validator.error(&[key("s3"), key("bind")], format!("Invalid bind address `{}`", bind));
+            }
+        }
+        if s3.max_put_size == Some(0) {
+            validator.error(&[key("s3"), key("max-put-size")], "max-put-size must be greater than 0".to_string());
+        }
+        if s3.credentials.is_empty() {
+            validator.warn(&[key("s3"), key("credentials")], "No credentials, all S3 requests will be rejected".to_string());
+        }
+        let mut access_keys = HashSet::new();
+        for (index, credential) in s3.credentials.iter().enumerate() {
+            let path = [key("s3"), key("credentials"), Segment::Index(index)];
+            if !access_keys.insert(credential.access_key.as_str()) {
+                validator.error(&with(&path, "access-key"), format!("Duplicate access key `{}`", credential.access_key));
+            }
+            if credential.secret_key.expose().is_empty() {
+                validator.error(&with(&path, "secret-key"), "Secret key must not be empty".to_string());
+            }
+            match config.power_config(&credential.power) {
+                None => validator.error(&with(&path, "power"), format!("Unknown power `{}`", credential.power)),
+                Some(power_config) => {
+                    let buckets = power_config.buckets.as_deref().unwrap_or_default();
+                    if let Some(bucket) = &credential.bucket {
+                        if !buckets.is_empty() && !buckets.contains(bucket) {
+                            validator.error(&with(&path, "bucket"), format!("Bucket `{}` is not in buckets of power `{}`", bucket, credential.power));
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    if let Some(AuthType::Bearer(_)) = &config.auth_type {
+        if !has_redis {
+            validator.error(&[key("auth-type")], "Bearer auth requires a redis-config".to_string());
+        }
+    }
+}
+
+fn validate_redis_configs(validator: &mut Validator, base: &[Segment], configs: &[crate::config::redis_config::RedisConfig]) {
+    for (index, redis) in configs.iter().enumerate() {
+        let mut path = base.to_vec();
+        path.push(Segment::Index(index));
+        match redis.mode {
+            RedisMode::Standalone => {
+                if redis.host.trim().is_empty() {
+                    validator.error(&with(&path, "host"), "Redis host must not be empty".to_string());
+                } else if redis.host.contains('/') && !redis.host.starts_with("redis://") && !redis.host.starts_with("rediss://") {
+                    validator.error(&with(&path, "host"), format!("Invalid Redis host `{}`", redis.host));
+                }
+            }
+            RedisMode::Cluster | RedisMode::Sentinel => {
+                if redis.nodes.as_deref().unwrap_or_default().is_empty() {
+                    validator.error(&with(&path, "nodes"), format!("{:?} mode requires nodes", redis.mode));
+                }
+                if redis.mode == RedisMode::Sentinel && redis.master_name.is_none() {
+                    validator.error(&with(&path, "master-name"), "Sentinel mode requires master-name".to_string());
+                }
+            }
+        }
+        if redis.timeout_ms == Some(0) {
+            validator.error(&with(&path, "timeout-ms"), "timeout-ms must be greater than 0".to_string());
+        }
+    }
+}
+
+// mirror.sources、replication.targets 中引用的 power 必须存在且不能是自身
+fn validate_power_refs(validator: &mut Validator, path: &[Segment], power_key: &str, refs: &[String], powers: &HashMap<String, PowerConfig>) {
+    if refs.is_empty() {
+        validator.warn(path, "No power listed".to_string());
+    }
+    let mut seen = HashSet::new();
+    for (index, target) in refs.iter().enumerate() {
+        let mut path = path.to_vec();
+        path.push(Segment::Index(index));
+        if target == power_key {
+            validator.error(&path, "A power cannot reference itself".to_string());
+        } else if !powers.contains_key(target) {
+            validator.error(&path, format!("Unknown power `{}`", target));
+        } else if !seen.insert(target) {
+            validator.warn(&path, format!("Duplicate power `{}`", target));
+        }
+    }
+}
+
+fn validate_minio_configs(validator: &mut Validator, base: &[Segment], configs: &[MinioConfig]) {
+    for (index, minio) in configs.iter().enumerate() {
+        let mut path = base.to_vec();
+        path.push(Segment::Index(index));
+
+        if let Err(message) = validate_endpoint(&minio.endpoint) {
+            validator.error(&with(&path, "endpoint"), message);
+        }
+        if minio.access_key.is_empty() {
+            validator.warn(&with(&path, "access-key"), "Empty access-key, requests will be anonymous".to_string());
+        }
+        if minio.max_pool_size == Some(0) {
+            validator.error(&with(&path, "max-pool-size"), "max-pool-size must be greater than 0".to_string());
+        }
+        if let (Some(idle), Some(max)) = (minio.idle_pool_size, minio.max_pool_size) {
+            if idle > max {
+                validator.warn(&with(&path, "idle-pool-size"), format!("idle-pool-size {} is larger than max-pool-size {}", idle, max));
+            }
+        }
+        if minio.region.as_deref().is_some_and(|region| region.trim().is_empty()) {
+            validator.error(&with(&path, "region"), "region must not be empty".to_string());
+        }
+        if minio.session_token.as_ref().is_some_and(|token| token.expose().is_empty()) {
+            validator.error(&with(&path, "session-token"), "session-token must not be empty".to_string());
+        }
+        if minio.addressing == Addressing::Virtual {
+            // 桶名会拼到域名前，IP 和 localhost 无法解析
+            let host = url::Url::parse(&minio.endpoint).ok().and_then(|url| url.host().map(|host| host.to_owned()));
+            if matches!(&host, Some(url::Host::Ipv4(_) | url::Host::Ipv6(_))) || matches!(&host, Some(url::Host::Domain(domain)) if domain == "localhost") {
+                validator.warn(&with(&path, "addressing"), "Virtual-host addressing needs a domain endpoint, use `path`".to_string());
+            }
+        }
+        if let Some(ca_cert) = &minio.ca_cert {
+            if !Path::new(ca_cert).is_file() {
+                validator.error(&with(&path, "ca-cert"), format!("CA certificate `{}` does not exist", ca_cert));
+            } else if !minio.endpoint.starts_with("https://") {
+                validator.warn(&with(&path, "ca-cert"), "ca-cert is only used by https endpoints".to_string());
+            }
+        }
+    }
+}
+
+fn validate_rewrite_rule(validator: &mut Validator, path: &[Segment], rule: &RewriteRule) {
+    if rule.from.is_some() && rule.regex.is_some() {
+        validator.error(path, "Use either `from` or `regex`, not both".to_string());
+        return;
+    }
+    // 改写结果不能以 `/` 开头，也不能包含 `..`，避免访问到公开路径之外的对象
+    for (field, value) in [("to", &rule.to), ("add-prefix", &rule.add_prefix)] {
+        match value {
+            Some(value) if value.starts_with('/') => {
+                validator.error(&with(path, field), format!("`{}` must not start with `/`", value))
+            }
+            Some(value) if rewrite::has_parent_segment(value) => {
+                validator.error(&with(path, field), format!("`{}` must not contain `..`", value))
+            }
+            _ => {}
+        }
+    }
+    if rule.from.is_none() && rule.regex.is_none() {
+        if rule.to.is_some() {
+            validator.error(&with(path, "to"), "`to` requires `from` or `regex`".to_string());
+        } else if rule.strip_prefix.is_none() && rule.add_prefix.is_none() {
+            validator.error(path, "Rewrite rule has no `from`, `regex`, `strip-prefix` or `add-prefix`".to_string());
+        }
+        return;
+    }
+
+    let (field, pattern) = match (&rule.from, &rule.regex) {
+        (Some(from), _) => ("from", rewrite::template_regex(from)),
+        (_, Some(regex)) => ("regex", regex.clone()),
+        _ => return,
+    };
+    let regex = match regex::Regex::new(&pattern) {
+        Ok(regex) => regex,
+        Err(e) => {
+            validator.error(&with(path, field), format!("Invalid pattern: {}", e));
+            return;
+        }
+    };
+
+    // 模板规则中 to 引用的变量必须在 from 中声明
+    if let (Some(_), Some(to)) = (&rule.from, &rule.to) {
+        let names: Vec<&str> = regex.capture_names().flatten().collect();
+        for part in to.split('{').skip(1) {
+            let name = part.split('}').next().unwrap_or("").trim_end_matches('*');
+            if !names.contains(&name) {
+                validator.error(&with(path, "to"), format!("Unknown variable `{{{}}}` in `to`", name));
+            }
+        }
+    }
+}
+
+pub fn validate_endpoint(endpoint: &str) -> Result<(), String> {
+    let url = url::Url::parse(endpoint).map_err(|e| format!("Invalid MinIO endpoint `{}`: {}", endpoint, e))?;
+    if url.scheme() != "http" && url.scheme() != "https" {
+        return Err(format!("MinIO endpoint `{}` must use http or https", endpoint));
+    }
+    if url.host_str().unwrap_or("").is_empty() {
+        return Err(format!("MinIO endpoint `{}` has no host", endpoint));
+    }
+    if url.path() != "/" && !url.path().is_empty() {
+        return Err(format!("MinIO endpoint `{}` must not contain a path", endpoint));
+    }
+    if url.query().is_some() {
+        return Err(format!("MinIO endpoint `{}` must not contain a query", endpoint));
+    }
+    Ok(())
+}
+
+fn key(name: &str) -> Segment {
+    Segment::Key(name.to_string())
+}
+
+fn with(path: &[Segment], name: &str) -> Vec<Segment> {
+    let mut path = path.to_vec();
+    path.push(key(name));
+    path
+}
+
+fn get_mut<'v>(value: &'v mut Value, path: &[Segment]) -> Option<&'v mut Value> {
+    path.iter().try_fold(value, |value, segment| match segment {
+        Segment::Key(k) => value.get_mut(k.as_str()),
+        Segment::Index(i) => value.get_mut(*i),
+    })
+}
+
+fn ignored_path(path: &serde_ignored::Path) -> Vec<Segment> {
+    match path {
+        serde_ignored::Path::Root => Vec::new(),
+        serde_ignored::Path::Seq { parent, index } => {
+            let mut segments = ignored_path(parent);
+            segments.push(Segment::Index(*index));
+            segments
+        }
+        serde_ignored::Path::Map { parent, key } => {
+            let mut segments = ignored_path(parent);
+            segments.push(Segment::Key(key.clone()));
+            segments
+        }
+        serde_ignored::Path::Some { parent }
+        | serde_ignored::Path::NewtypeStruct { parent }
+        | serde_ignored::Path::NewtypeVariant { parent } => ignored_path(parent),
+    }
+}
+
+fn format_path(path: &[Segment]) -> String {
+    if path.is_empty() {
+        return ".".to_string();
+    }
+    let mut formatted = String::new();
+    for segment in path {
+        match segment {
+            Segment::Key(k) => {
+                if !formatted.is_empty() {
+                    formatted.push('.');
+                }
+                formatted.push_str(k);
+            }
+            Segment::Index(i) => formatted.push_str(&format!("[{}]", i)),
+        }
+    }
+    formatted
+}
+
+// 按缩进在原始文本中查找配置路径所在的行号(从 1 开始)
+fn locate(content: &str, path: &[Segment]) -> Option<usize> {
+    let lines: Vec<&str> = content.lines().collect();
+    let mut start = 0;
+    // 当前所在块的缩进，只在缩进更深的行中查找
+    let mut parent_indent: isize = -1;
+    // 数组元素的第一个 key 与 `-` 在同一行
+    let mut inline_first = false;
+    let mut found = None;
+
+    for segment in path {
+        let mut matched = None;
+        let mut seq_index = 0;
+        let mut seq_indent: Option<usize> = None;
+        for (i, line) in lines.iter().enumerate().skip(start) {
+            let trimmed = line.trim_start();
+            if trimmed.is_empty() || trimmed.starts_with('#') {
+                continue;
+            }
+            let indent = line.len() - trimmed.len();
+            let is_first = inline_first && i == start;
+            // 数组的 `-` 允许与父级 key 缩进相同
+            let same_level_item = indent as isize == parent_indent
+                && trimmed.starts_with('-')
+                && matches!(segment, Segment::Index(_));
+            if !is_first && indent as isize <= parent_indent && !same_level_item {
+                break;
+            }
+            match segment {
+                Segment::Key(k) => {
+                    let (key_indent, body) = match trimmed.strip_prefix("- ") {
+                        Some(body) if is_first => (indent + 2, body.trim_start()),
+                        _ => (indent, trimmed),
+                    };
+                    let key_text = body.split(':').next().unwrap_or("").trim().trim_matches(|c| c == '"' || c == '\'');
+                    if key_text == k && body.contains(':') {
+                        matched = Some((i, key_indent as isize, false));
+                        break;
+                    }
+                }
+                Segment::Index(n) => {
+                    if !trimmed.starts_with('-') {
+                        continue;
+                    }
+                    match seq_indent {
+                        None => seq_indent = Some(indent),
+                        Some(s) if s != indent => continue,
+                        _ => {}
+                    }
+                    if seq_index == *n {
+                        matched = Some((i, indent as isize, true));
+                        break;
+                    }
+                    seq_index += 1;
+                }
+            }
+        }
+        let (line, indent, inline) = matched?;
+        found = Some(line + 1);
+        start = line;
+        parent_indent = indent;
+        inline_first = inline;
+        if !inline {
+            start = line + 1;
+        }
+    }
+    found
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BASE: &str = "power:\n  local:\n    backend: fs\n    root: /tmp\n";
+
+    fn errors(issues: &[ConfigIssue]) -> Vec<&ConfigIssue> {
+        issues.iter().filter(|issue| issue.level == IssueLevel::Error).collect()
+    }
+
+    #[test]
+    fn placeholders_are_replaced() {
+        std::env::set_var("LOADER_TEST_PORT", "9100");
+        std::env::remove_var("LOADER_TEST_UNSET");
+        let yaml = format!("server-port: ${{LOADER_TEST_PORT}}\nmatch-prefix: /${{LOADER_TEST_UNSET:-files}}\n{}", BASE);
+        let (config, _) = parse_config(&yaml).unwrap();
+        assert_eq!(config.server_port, Some(9100));
+        assert_eq!(config.match_prefix.as_deref(), Some("/files"));
+    }
+
+    #[test]
+    fn quoted_placeholders_stay_strings() {
+        std::env::set_var("LOADER_TEST_NUMBER", "9200");
+        // 注释中出现加引号的写法时，未加引号的值仍按数字解析
+        let yaml = format!(
+            "server-port: ${{LOADER_TEST_NUMBER}} # \"${{LOADER_TEST_NUMBER}}\"\n{}    bucket-name: \"${{LOADER_TEST_NUMBER}}\"\n    buckets: ['${{LOADER_TEST_NUMBER}}']\n",
+            BASE
+        );
+        let (config, _) = parse_config(&yaml).unwrap();
+        assert_eq!(config.server_port, Some(9200));
+        let power = config.power_config("local").unwrap();
+        assert_eq!(power.bucket_name.as_deref(), Some("9200"));
+        assert_eq!(power.buckets.as_deref(), Some(&["9200".to_string()][..]));
+    }
+
+    #[test]
+    fn multi_line_scalars_stay_strings() {
+        std::env::set_var("LOADER_TEST_SUFFIX", "1");
+        let yaml = format!("{}    bucket-name: >-\n      ${{LOADER_TEST_SUFFIX}}\n", BASE);
+        let (config, _) = parse_config(&yaml).unwrap();
+        assert_eq!(config.power_config("local").unwrap().bucket_name.as_deref(), Some("1"));
+    }
+
+    #[test]
+    fn escaped_placeholder_is_literal() {
+        let yaml = format!("{}    bucket-name: a$${{NOT_A_VAR}}b\n", BASE);
+        let (config, _) = parse_config(&yaml).unwrap();
+        assert_eq!(config.power_config("local").unwrap().bucket_name.as_deref(), Some("a${NOT_A_VAR}b"));
+    }
+
+    #[test]
+    fn missing_variable_is_an_error() {
+        std::env::remove_var("LOADER_TEST_MISSING");
+        std::env::remove_var("LOADER_TEST_MISSING_FILE");
+        let yaml = format!("{}    bucket-name: ${{LOADER_TEST_MISSING}}\n", BASE);
+        let issues = parse_config(&yaml).unwrap_err();
+        let errors = errors(&issues);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].path, "power.local.bucket-name");
+        assert_eq!(errors[0].line, Some(5));
+        assert!(errors[0].message.contains("LOADER_TEST_MISSING"));
+    }
+
+    #[test]
+    fn unknown_keys_are_rejected() {
+        let yaml = format!("server-port: 9000\n{}    bucket-nmae: a\n", BASE);
+        let issues = parse_config(&yaml).unwrap_err();
+        let errors = errors(&issues);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].path, "power.local.bucket-nmae");
+        assert_eq!(errors[0].line, Some(6));
+        assert_eq!(errors[0].message, "Unknown key `bucket-nmae`");
+    }
+
+    #[test]
+    fn locate_reports_lines() {
+        let content = "\
+# comment
+power:
+  local:
+    root: /tmp
+
+    minio-config:
+      - endpoint: a
+        secret-key: x
+      -
+        endpoint: b
+  other:
+    root: /srv
+";
+        let path = |segments: &[Segment]| locate(content, segments);
+        assert_eq!(path(&[key("power")]), Some(2));
+        assert_eq!(path(&[key("power"), key("local"), key("root")]), Some(4));
+        assert_eq!(path(&[key("power"), key("local"), key("minio-config"), Segment::Index(0)]), Some(7));
+        assert_eq!(path(&[key("power"), key("local"), key("minio-config"), Segment::Index(0), key("secret-key")]), Some(8));
+        assert_eq!(path(&[key("power"), key("local"), key("minio-config"), Segment::Index(1), key("endpoint")]), Some(10));
+        assert_eq!(path(&[key("power"), key("other"), key("root")]), Some(12));
+        assert_eq!(path(&[key("power"), key("other"), key("bucket-name")]), None);
+    }
+
+    #[test]
+    fn rewrite_targets_are_checked() {
+        let yaml = format!(
+            "{}    rewrite:
+      - from: a/{{x}}
+        to: /b/{{x}}
+      - regex: (.*)
+        to: ../$1
+      - strip-prefix: c/
+        add-prefix: /d/
+      - from: e/{{x}}
+        to: f/{{x}}
+",
+            BASE
+        );
+        let issues = parse_config(&yaml).unwrap_err();
+        let errors: Vec<(&str, Option<usize>)> = errors(&issues).iter().map(|issue| (issue.path.as_str(), issue.line)).collect();
+        assert_eq!(errors, [
+            ("power.local.rewrite[0].to", Some(7)),
+            ("power.local.rewrite[1].to", Some(9)),
+            ("power.local.rewrite[2].add-prefix", Some(11)),
+        ]);
+    }
+
+    fn overridden(content: &str, vars: &[(&str, &str)]) -> (Value, Vec<ConfigIssue>) {
+        let mut validator = Validator { content, issues: Vec::new() };
+        let mut value: Value = serde_yaml::from_str(content).unwrap();
+        let vars = vars.iter().map(|(name, value)| (name.to_string(), value.to_string()));
+        apply_env_overrides(&mut validator, &mut value, vars.collect::<Vec<_>>().into_iter());
+        (value, validator.issues)
+    }
+
+    #[test]
+    fn env_overrides_nested_keys() {
+        let (value, issues) = overridden(BASE, &[
+            ("WARP_MINIO__POWER__local__BUCKET_NAME", "atom"),
+            ("WARP_MINIO__POWER__LOCAL__ROOT", "/srv"),
+            ("OTHER__POWER__LOCAL__ROOT", "/ignored"),
+        ]);
+        assert!(issues.is_empty());
+        assert_eq!(value["power"]["local"]["bucket-name"], Value::from("atom"));
+        assert_eq!(value["power"]["local"]["root"], Value::from("/srv"));
+    }
+
+    #[test]
+    fn env_overrides_list_indexes() {
+        let content = "default:\n  minio-config:\n    - endpoint: http://a\n      secret-key: old\n";
+        let (value, issues) = overridden(content, &[
+            ("WARP_MINIO__DEFAULT__MINIO_CONFIG__0__SECRET_KEY", "new"),
+            ("WARP_MINIO__DEFAULT__MINIO_CONFIG__1__ENDPOINT", "http://b"),
+            ("WARP_MINIO__DEFAULT__MINIO_CONFIG__3__ENDPOINT", "http://d"),
+            ("WARP_MINIO__DEFAULT__MINIO_CONFIG__X__ENDPOINT", "http://x"),
+        ]);
+        let configs = value["default"]["minio-config"].as_sequence().unwrap();
+        assert_eq!(configs.len(), 2);
+        assert_eq!(configs[0]["secret-key"], Value::from("new"));
+        assert_eq!(configs[0]["endpoint"], Value::from("http://a"));
+        assert_eq!(configs[1]["endpoint"], Value::from("http://b"));
+        let messages: Vec<&str> = issues.iter().map(|issue| issue.message.as_str()).collect();
+        assert_eq!(messages.len(), 2);
+        assert!(messages[0].contains("Index 3 is out of range"));
+        assert!(messages[1].contains("`X` is not an index of a list"));
+    }
+
+    #[test]
+    fn env_overrides_keep_value_types() {
+        let content = format!("server-port: 9000\n{}    bucket-name: atom\n", BASE);
+        let (value, issues) = overridden(&content, &[
+            ("WARP_MINIO__SERVER_PORT", "9300"),
+            ("WARP_MINIO__POWER__LOCAL__BUCKET_NAME", "123"),
+            ("WARP_MINIO__PARSING_CONTENT_TYPE", "true"),
+        ]);
+        assert!(issues.is_empty());
+        // 文件中为字符串的值保持字符串，其余按 YAML 标量解析
+        assert_eq!(value["server-port"], Value::from(9300));
+        assert_eq!(value["power"]["local"]["bucket-name"], Value::from("123"));
+        assert_eq!(value["parsing-content-type"], Value::from(true));
+        let config: WarpConfig = serde_yaml::from_value(value).unwrap();
+        assert_eq!(config.server_port, Some(9300));
+        assert!(config.parsing_content_type);
+    }
+
+    #[test]
+    fn env_overrides_add_missing_keys() {
+        let (value, issues) = overridden(BASE, &[
+            ("WARP_MINIO__POWER__LOCAL__UPLOAD__MAX_PUT_SIZE", "1024"),
+            ("WARP_MINIO__POWER__ARCHIVE__BACKEND", "fs"),
+        ]);
+        assert!(issues.is_empty());
+        assert_eq!(value["power"]["local"]["upload"]["max-put-size"], Value::from(1024));
+        assert_eq!(value["power"]["archive"]["backend"], Value::from("fs"));
+        assert_eq!(value["power"]["local"]["root"], Value::from("/tmp"));
+    }
+
+    fn secret_file(name: &str, content: &str) -> String {
+        let path = std::env::temp_dir().join(format!("warp-minio-loader-{}-{}", std::process::id(), name));
+        std::fs::write(&path, content).unwrap();
+        path.display().to_string()
+    }
+
+    fn resolved(content: &str) -> (Value, Vec<ConfigIssue>) {
+        let mut validator = Validator { content, issues: Vec::new() };
+        let mut value: Value = serde_yaml::from_str(content).unwrap();
+        resolve_secret_files(&mut validator, &mut value, &mut Vec::new());
+        (value, validator.issues)
+    }
+
+    #[test]
+    fn secret_files_trim_trailing_newline() {
+        let file = secret_file("trim", "s3cr3t \r\n\n");
+        let content = format!("default:\n  redis-config:\n    - host: 127.0.0.1\n      password-file: {}\n", file);
+        let (value, issues) = resolved(&content);
+        assert!(issues.is_empty());
+        let redis = &value["default"]["redis-config"][0];
+        assert_eq!(redis["password"], Value::from("s3cr3t "));
+        assert!(redis.get("password-file").is_none());
+
+        std::env::set_var("LOADER_TEST_SECRET_FILE", &file);
+        std::env::remove_var("LOADER_TEST_SECRET");
+        assert_eq!(interpolate("${LOADER_TEST_SECRET}").unwrap(), "s3cr3t ");
+    }
+
+    #[test]
+    fn unreadable_secret_file_is_an_error() {
+        let content = "power:\n  local:\n    minio-config:\n      - secret-key-file: /nonexistent/secret\n";
+        let (_, issues) = resolved(content);
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].path, "power.local.minio-config[0].secret-key-file");
+        assert_eq!(issues[0].line, Some(4));
+        assert!(issues[0].message.contains("Failed to read secret file `/nonexistent/secret`"));
+
+        std::env::set_var("LOADER_TEST_UNREADABLE_FILE", "/nonexistent/secret");
+        std::env::remove_var("LOADER_TEST_UNREADABLE");
+        let message = interpolate("${LOADER_TEST_UNREADABLE}").unwrap_err();
+        assert!(message.contains("LOADER_TEST_UNREADABLE_FILE"));
+    }
+
+    #[test]
+    fn inline_value_and_secret_file_conflict() {
+        let file = secret_file("conflict", "from-file");
+        let content = format!("default:\n  minio-config:\n    - secret-key: inline\n      secret-key-file: {}\n", file);
+        let (value, issues) = resolved(&content);
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].level, IssueLevel::Error);
+        assert_eq!(issues[0].line, Some(4));
+        assert_eq!(issues[0].message, "Both `secret-key` and `secret-key-file` are set");
+        assert_eq!(value["default"]["minio-config"][0]["secret-key"], Value::from("inline"));
+    }
+}
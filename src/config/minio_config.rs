@@ -1,16 +1,43 @@
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
-#[derive(Deserialize, Debug, Clone)]
+use crate::config::secret::Secret;
+
+// S3 请求的寻址方式
+#[derive(Deserialize, Serialize, Debug, Default, Clone, Copy, PartialEq)]
+pub enum Addressing {
+    // 由客户端判断，AWS 和阿里云 OSS 的域名使用 virtual-host 方式，其余使用 path 方式
+    #[default]
+    #[serde(rename = "auto")]
+    Auto,
+    // 桶名在路径中：https://endpoint/bucket/key
+    #[serde(rename = "path")]
+    Path,
+    // 桶名在域名中：https://bucket.endpoint/key
+    #[serde(rename = "virtual")]
+    Virtual,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
 pub struct MinioConfig {
     #[serde(rename = "access-key")]
     pub(crate) access_key: String,
     #[serde(rename = "secret-key")]
-    pub(crate) secret_key: String,
+    pub(crate) secret_key: Secret,
     #[serde(rename = "endpoint")]
     pub(crate) endpoint: String,
-    #[serde(rename = "max-pool-idle")]
+    #[serde(rename = "max-pool-size", alias = "max-pool-idle")]
     pub(crate) max_pool_size: Option<u32>,
     #[serde(rename = "idle-pool-size")]
     pub(crate) idle_pool_size: Option<u32>,
+    // 签名使用的区域，未配置时从 endpoint 推断或向服务端查询桶所在区域
+    #[serde(rename = "region")]
+    pub(crate) region: Option<String>,
+    #[serde(rename = "addressing", default)]
+    pub(crate) addressing: Addressing,
+    // 临时凭证(STS)的会话令牌
+    #[serde(rename = "session-token")]
+    pub(crate) session_token: Option<Secret>,
+    // 自签名或私有 CA 的证书文件(PEM)
+    #[serde(rename = "ca-cert")]
+    pub(crate) ca_cert: Option<String>,
 }
-
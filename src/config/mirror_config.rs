@@ -0,0 +1,14 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+pub struct MirrorConfig {
+    // 当前 power 返回 404 时依次查找的 power
+    #[serde(rename = "sources", default)]
+    pub(crate) sources: Vec<String>,
+    // 从后续来源读到对象后，在后台复制到当前 power
+    #[serde(rename = "read-through", default)]
+    pub(crate) read_through: bool,
+    // 回写对象的大小上限(字节)，默认 64MiB，超过时只转发不复制
+    #[serde(rename = "max-copy-size")]
+    pub(crate) max_copy_size: Option<u64>,
+}
@@ -1,5 +1,6 @@
-use std::{env, fs};
+use std::env;
 use std::path::Path;
+use std::sync::{Arc, OnceLock, RwLock};
 
 use lazy_static::lazy_static;
 
@@ -10,34 +11,121 @@ pub mod power_config;
 pub mod minio_config;
 pub mod default_config;
 pub mod warp_config;
+pub mod tracing_config;
+pub mod fallback_config;
+pub mod header_config;
+pub mod loader;
+pub mod secret;
+pub mod admin_config;
+pub mod rewrite_config;
+pub mod s3_config;
+pub mod webdav_config;
+pub mod mirror_config;
+pub mod upload_config;
+pub mod replication_config;
+pub mod hot_cache_config;
+pub mod presign_config;
+pub mod fetch_config;
+pub mod coalesce_config;
 
 
 // 环境变量名称
-const CONFIG_PATH_KEY: &'static str = "WARP_MINIO_CONFIG_PATH";
+const CONFIG_PATH_KEY: &str = "WARP_MINIO_CONFIG_PATH";
 
 pub const BEARER_PREFIX_LEN: usize = 7;
 
 // 文件路径前缀
-pub const URL_PREFIX: &'static str = "/minio";
+pub const URL_PREFIX: &str = "/minio";
 
 // 配置路径
-pub const MINIO_CONFIG_KEY_PREFIX: &'static str = "sys_oss:";
+#[allow(dead_code)]
+pub const MINIO_CONFIG_KEY_PREFIX: &str = "sys_oss:";
 
 // 端口
 pub const PORT: u16 = 9928;
 
+// 请求ID请求头
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+// 默认服务名称
+pub const SERVICE_NAME: &str = "warp_minio_server";
+
+// 命令行 --config 指定的配置路径，优先于环境变量
+static CONFIG_PATH_OVERRIDE: OnceLock<String> = OnceLock::new();
+
+// 必须在第一次访问 WARP_MINIO_CONFIG 之前调用
+pub fn set_config_path(path: String) {
+    let _ = CONFIG_PATH_OVERRIDE.set(path);
+}
+
+pub fn config_path() -> String {
+    CONFIG_PATH_OVERRIDE
+        .get()
+        .cloned()
+        .unwrap_or_else(|| env::var(CONFIG_PATH_KEY).unwrap_or_else(|_| "config.yaml".to_string()))
+}
+
 lazy_static! {
-  pub static ref WARP_MINIO_CONFIG: WarpConfig = {
-        let config_path = env::var(CONFIG_PATH_KEY).unwrap_or_else(|_| "config.yaml".to_string());
-        let path = Path::new(&config_path);
-        log::info!("Config path: {:?}", path);
-        let config = fs::read_to_string(path)
-            .map_err(|e| log::error!("Failed to read config file: {}", e))
-            .and_then(|content| serde_yaml::from_str(&content)
-                .map_err(|e| log::error!("Failed to parse config file: {}", e)))
-            .unwrap_or_else(|_| WarpConfig::default());
-        log::info!("Config: {:?}", config);
-        config
+    static ref WARP_MINIO_CONFIG: RwLock<Arc<WarpConfig>> = {
+        let config_path = config_path();
+        match read_config(&config_path) {
+            Ok(config) => RwLock::new(Arc::new(config)),
+            Err(errors) => {
+                // 启动时配置有误直接退出，避免带着错误配置启动
+                log::error!("Found {} error(s) in config file, exiting", errors);
+                std::process::exit(1);
+            }
+        }
     };
 }
 
+// 当前生效的配置，重新加载后返回新的配置
+pub fn current() -> Arc<WarpConfig> {
+    WARP_MINIO_CONFIG.read().unwrap_or_else(|e| e.into_inner()).clone()
+}
+
+// 重新读取配置文件，配置有误时返回错误数量；读取到的配置需要调用 replace 才会生效
+pub fn reread() -> Result<WarpConfig, usize> {
+    read_config(&config_path())
+}
+
+// 替换当前生效的配置
+pub fn replace(config: WarpConfig) -> Arc<WarpConfig> {
+    let config = Arc::new(config);
+    *WARP_MINIO_CONFIG.write().unwrap_or_else(|e| e.into_inner()) = config.clone();
+    log::info!("Config reloaded");
+    config
+}
+
+fn read_config(config_path: &str) -> Result<WarpConfig, usize> {
+    let path = Path::new(config_path);
+    log::info!("Config path: {:?}", path);
+    let loaded = if path.exists() {
+        loader::load_config(path)
+    } else {
+        // 没有配置文件时仍然应用环境变量覆盖
+        log::warn!("Config file {:?} not found, using default config", path);
+        loader::parse_config("{}")
+    };
+    match loaded {
+        Ok((config, warnings)) => {
+            for warning in warnings {
+                log::warn!("{}: {}", config_path, warning);
+            }
+            log::info!("Config: {:?}", config);
+            Ok(config)
+        }
+        Err(issues) => {
+            let mut errors = 0;
+            for issue in &issues {
+                if issue.level == loader::IssueLevel::Error {
+                    errors += 1;
+                    log::error!("{}: {}", config_path, issue);
+                } else {
+                    log::warn!("{}: {}", config_path, issue);
+                }
+            }
+            Err(errors)
+        }
+    }
+}
@@ -1,18 +1,99 @@
 use std::collections::HashMap;
 
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
+use crate::config::coalesce_config::CoalesceConfig;
+use crate::config::fallback_config::FallbackConfig;
+use crate::config::fetch_config::FetchConfig;
+use crate::config::header_config::HeaderConfig;
+use crate::config::hot_cache_config::HotCacheConfig;
 use crate::config::minio_config::MinioConfig;
+use crate::config::mirror_config::MirrorConfig;
+use crate::config::presign_config::PresignConfig;
 use crate::config::redis_config::RedisConfig;
+use crate::config::replication_config::ReplicationConfig;
+use crate::config::rewrite_config::RewriteRule;
+use crate::config::upload_config::UploadConfig;
+use crate::config::webdav_config::WebdavConfig;
 
-#[derive(Deserialize, Debug, Default)]
+// 桶的寻址方式
+#[derive(Deserialize, Serialize, Debug, Default, Clone, PartialEq)]
+pub enum BucketMode {
+    // 固定使用 bucket-name，路径为 /config_key/key
+    #[default]
+    #[serde(rename = "fixed")]
+    Fixed,
+    // 路径中携带桶名，路径为 /config_key/bucket/key
+    #[serde(rename = "path")]
+    Path,
+}
+
+// 存储后端
+#[derive(Deserialize, Serialize, Debug, Default, Clone, PartialEq)]
+pub enum BackendKind {
+    #[default]
+    #[serde(rename = "minio")]
+    Minio,
+    // 本地目录，不需要 MinIO
+    #[serde(rename = "fs")]
+    Fs,
+}
+
+#[derive(Deserialize, Serialize, Debug, Default)]
 pub struct PowerConfig {
+    #[serde(rename = "backend", default)]
+    pub(crate) backend: BackendKind,
+    // fs 后端的根目录，path 模式下每个桶为其中的一个子目录
+    #[serde(rename = "root")]
+    pub(crate) root: Option<String>,
+    // 绑定的域名，支持 `*.b.com` 通配符，匹配到时路径中不需要 config_key
+    #[serde(rename = "hosts")]
+    pub(crate) hosts: Option<Vec<String>>,
     #[serde(rename = "bucket-name")]
     pub(crate) bucket_name: Option<String>,
+    // 允许访问的桶，path 模式下路径中的桶必须在列表中
+    #[serde(rename = "buckets")]
+    pub(crate) buckets: Option<Vec<String>>,
+    #[serde(rename = "bucket-mode", default)]
+    pub(crate) bucket_mode: BucketMode,
     #[serde(rename = "redis-config")]
     pub(crate) redis_config: Option<Vec<RedisConfig>>,
     #[serde(rename = "minio-config")]
     pub(crate) minio_config: Option<Vec<MinioConfig>>,
+    #[allow(dead_code)]
     #[serde(rename = "convert")]
     pub(crate) convert: Option<HashMap<String, String>>,
+    // 按状态码配置的兜底响应
+    #[serde(rename = "fallback")]
+    pub(crate) fallback: Option<HashMap<u16, FallbackConfig>>,
+    // 响应头策略
+    #[serde(rename = "headers")]
+    pub(crate) headers: Option<HeaderConfig>,
+    // 公开路径到对象 key 的改写规则
+    #[serde(rename = "rewrite")]
+    pub(crate) rewrite: Option<Vec<RewriteRule>>,
+    // 配置后可以通过 /dav/{config_key}/ 以 WebDAV 方式访问
+    #[serde(rename = "webdav")]
+    pub(crate) webdav: Option<WebdavConfig>,
+    // 对象不存在时按顺序回源到其他 power，用于迁移期间读取旧集群
+    #[serde(rename = "mirror")]
+    pub(crate) mirror: Option<MirrorConfig>,
+    // 配置后代理接受 PUT 上传对象
+    #[serde(rename = "upload")]
+    pub(crate) upload: Option<UploadConfig>,
+    // 通过代理上传的对象异步复制到其他 power
+    #[serde(rename = "replication")]
+    pub(crate) replication: Option<ReplicationConfig>,
+    // 相同对象、相同 Range 的并发请求共用一次上游读取
+    #[serde(rename = "coalesce")]
+    pub(crate) coalesce: Option<CoalesceConfig>,
+    // 小对象的内存缓存
+    #[serde(rename = "hot-cache")]
+    pub(crate) hot_cache: Option<HotCacheConfig>,
+    // 预签名地址的有效期和缓存
+    #[serde(rename = "presign")]
+    pub(crate) presign: Option<PresignConfig>,
+    // MinIO 后端读取对象的方式和上游超时
+    #[serde(rename = "fetch")]
+    pub(crate) fetch: Option<FetchConfig>,
 }
\ No newline at end of file
@@ -0,0 +1,11 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+pub struct PresignConfig {
+    // 预签名地址的有效期(秒)，默认 604800(7 天)，也是 S3 允许的最大值
+    #[serde(rename = "expiry-secs")]
+    pub(crate) expiry_secs: Option<u32>,
+    // 缓存预签名地址，过期前重复使用，默认开启
+    #[serde(rename = "cache")]
+    pub(crate) cache: Option<bool>,
+}
@@ -0,0 +1,14 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+pub struct ReplicationConfig {
+    // 异步复制的目标 power
+    #[serde(rename = "targets", default)]
+    pub(crate) targets: Vec<String>,
+    // 单个对象的最大尝试次数，默认 10 次，超过后记为失败
+    #[serde(rename = "max-attempts")]
+    pub(crate) max_attempts: Option<u32>,
+    // 第一次重试的间隔，之后每次翻倍，默认 5000ms，最长 10 分钟
+    #[serde(rename = "retry-interval-ms")]
+    pub(crate) retry_interval_ms: Option<u64>,
+}
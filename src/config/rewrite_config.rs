@@ -0,0 +1,21 @@
+use serde::{Deserialize, Serialize};
+
+// 对象 key 改写规则，按顺序匹配，只应用第一条匹配的规则
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct RewriteRule {
+    // 路径模板，如 /avatars/{uid}.png，`{name}` 匹配一段路径，`{name*}` 匹配多段
+    #[serde(rename = "from")]
+    pub(crate) from: Option<String>,
+    // 正则表达式，与 from 二选一，to 中使用 $1、${name} 引用捕获组
+    #[serde(rename = "regex")]
+    pub(crate) regex: Option<String>,
+    // 改写后的对象 key，from 规则中使用 {name} 引用变量
+    #[serde(rename = "to")]
+    pub(crate) to: Option<String>,
+    // 去掉的前缀，不以该前缀开头时规则不匹配
+    #[serde(rename = "strip-prefix")]
+    pub(crate) strip_prefix: Option<String>,
+    // 添加的前缀，在其他改写之后应用
+    #[serde(rename = "add-prefix")]
+    pub(crate) add_prefix: Option<String>,
+}
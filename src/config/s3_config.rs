@@ -0,0 +1,38 @@
+use serde::{Deserialize, Serialize};
+
+use crate::config::secret::Secret;
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct S3Config {
+    // S3 兼容接口端口，必须与 server-port、admin.port 不同
+    #[serde(rename = "port")]
+    pub(crate) port: u16,
+    // 监听地址，默认只监听本机
+    #[serde(rename = "bind")]
+    pub(crate) bind: Option<String>,
+    // GetBucketLocation 返回的区域，默认 us-east-1
+    #[serde(rename = "region")]
+    pub(crate) region: Option<String>,
+    // PutObject 请求体大小上限(字节)，默认 64MiB
+    #[serde(rename = "max-put-size")]
+    pub(crate) max_put_size: Option<u64>,
+    #[serde(rename = "credentials", default)]
+    pub(crate) credentials: Vec<S3Credential>,
+}
+
+// 由代理签发的访问凭证，每个凭证只能访问一个 power 下的桶
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct S3Credential {
+    #[serde(rename = "access-key")]
+    pub(crate) access_key: String,
+    #[serde(rename = "secret-key")]
+    pub(crate) secret_key: Secret,
+    #[serde(rename = "power")]
+    pub(crate) power: String,
+    // 允许访问的桶，未配置时为 power 的桶(path 模式下为 buckets 列表)
+    #[serde(rename = "bucket")]
+    pub(crate) bucket: Option<String>,
+    // 只读凭证不允许 PutObject
+    #[serde(rename = "read-only", default)]
+    pub(crate) read_only: bool,
+}
@@ -0,0 +1,13 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Deserialize, Serialize, Debug, Default, Clone)]
+pub struct TracingConfig {
+    // OTLP/HTTP 收集器地址，如 http://127.0.0.1:4318/v1/traces，为空时只输出本地日志
+    #[serde(rename = "otlp-endpoint")]
+    pub(crate) otlp_endpoint: Option<String>,
+    #[serde(rename = "service-name")]
+    pub(crate) service_name: Option<String>,
+    // 采样比例 0.0 ~ 1.0，默认全部采样
+    #[serde(rename = "sample-ratio")]
+    pub(crate) sample_ratio: Option<f64>,
+}
@@ -0,0 +1,8 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+pub struct UploadConfig {
+    // PUT 请求体大小上限(字节)，默认 64MiB
+    #[serde(rename = "max-put-size")]
+    pub(crate) max_put_size: Option<u64>,
+}
@@ -1,13 +1,19 @@
+use std::cmp::Reverse;
 use std::collections::HashMap;
 use rand::{thread_rng, seq::SliceRandom};
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
 use crate::auth::AuthType;
+use crate::config::admin_config::AdminConfig;
 use crate::config::default_config::DefaultConfig;
+use crate::config::minio_config::MinioConfig;
 use crate::config::power_config::PowerConfig;
 use crate::config::redis_config::RedisConfig;
+use crate::config::s3_config::S3Config;
+use crate::config::tracing_config::TracingConfig;
+use crate::utils::wildcard_match_ignore_case;
 
-#[derive(Deserialize, Debug, Default)]
+#[derive(Deserialize, Serialize, Debug, Default)]
 pub struct WarpConfig {
     #[serde(rename = "server-port")]
     pub(crate) server_port: Option<u16>,
@@ -15,12 +21,19 @@ pub struct WarpConfig {
     pub(crate) auth_type: Option<AuthType>,
     #[serde(rename = "match-prefix")]
     pub(crate) match_prefix: Option<String>,
-    #[serde(rename = "parsing-content-type")]
+    #[serde(rename = "parsing-content-type", default)]
     pub(crate) parsing_content_type: bool,
     #[serde(rename = "power")]
     pub(crate) power: Option<HashMap<String, PowerConfig>>,
-    #[serde(rename = "default")]
-    pub(crate) default: DefaultConfig
+    #[serde(rename = "default", default)]
+    pub(crate) default: DefaultConfig,
+    #[serde(rename = "tracing")]
+    pub(crate) tracing: Option<TracingConfig>,
+    #[serde(rename = "admin")]
+    pub(crate) admin: Option<AdminConfig>,
+    // S3 兼容接口
+    #[serde(rename = "s3")]
+    pub(crate) s3: Option<S3Config>,
 }
 
 impl WarpConfig {
@@ -57,12 +70,74 @@ impl WarpConfig {
         None
     }
 
+    // 固定使用 power(或 default)的第一个 Redis 配置，用于复制队列等需要所有实例读写同一个 Redis 的数据
+    pub fn pinned_redis_by_config_key(&self, config_key: &str) -> Option<RedisConfig> {
+        let redis_configs = self
+            .power_config(config_key)
+            .and_then(|power| power.redis_config.as_ref())
+            .or(self.default.redis_config.as_ref())?;
+        redis_configs.first().cloned()
+    }
+
+    // default 和全部 power 的 MinIO 配置
+    pub fn minio_configs(&self) -> impl Iterator<Item = &MinioConfig> {
+        let powers = self.power.iter().flat_map(|power| power.values());
+        self.default
+            .minio_config
+            .iter()
+            .chain(powers.filter_map(|power| power.minio_config.as_ref()))
+            .flatten()
+    }
+
+    pub fn power_config(&self, config_key: &str) -> Option<&PowerConfig> {
+        self.power.as_ref().and_then(|power| power.get(config_key))
+    }
+
+    // 按 Host 请求头查找 power，精确匹配优先，其次是最长的通配符；长度相同时取名称最小的 power，结果不受 HashMap 顺序影响
+    pub fn power_by_host(&self, host: &str) -> Option<&str> {
+        let host = strip_port(host);
+        let power = self.power.as_ref()?;
+        let mut best: Option<(&str, bool, usize)> = None;
+        for (key, config) in power {
+            for pattern in config.hosts.iter().flatten() {
+                let exact = !pattern.contains('*');
+                let matched = if exact {
+                    pattern.eq_ignore_ascii_case(host)
+                } else {
+                    wildcard_match_ignore_case(pattern, host)
+                };
+                if !matched {
+                    continue;
+                }
+                let better = match best {
+                    None => true,
+                    Some((best_key, best_exact, best_len)) => {
+                        (exact, pattern.len(), Reverse(key.as_str())) > (best_exact, best_len, Reverse(best_key))
+                    }
+                };
+                if better {
+                    best = Some((key, exact, pattern.len()));
+                }
+            }
+        }
+        best.map(|(key, _, _)| key)
+    }
+
     pub fn bucket_name(&self, config_key: String) -> Option<String> {
         if let Some(power) = &self.power {
            if let Some(config) = power.get(&config_key) {
-               return config.bucket_name.clone()
+               // 未配置 bucket-name 时使用 buckets 中的第一个
+               return config.bucket_name.clone().or_else(|| config.buckets.as_ref()?.first().cloned())
             }
         }
         None
     }
 }
+
+// 去掉 Host 中的端口，IPv6 地址保留方括号
+fn strip_port(host: &str) -> &str {
+    if host.starts_with('[') {
+        return host.split_once(']').map_or(host, |(addr, _)| &host[..addr.len() + 1]);
+    }
+    host.rsplit_once(':').map_or(host, |(name, _)| name)
+}
@@ -0,0 +1,11 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+pub struct WebdavConfig {
+    // 允许 PUT、DELETE、MKCOL、MOVE，默认只读
+    #[serde(rename = "writable", default)]
+    pub(crate) writable: bool,
+    // PUT 请求体大小上限(字节)，默认 64MiB
+    #[serde(rename = "max-put-size")]
+    pub(crate) max_put_size: Option<u64>,
+}
@@ -0,0 +1,606 @@
+use bytes::Bytes;
+use mime_guess::from_path;
+use minio::s3::utils::{to_http_header_value, Multimap, UtcTime};
+use percent_encoding::{utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+use tracing::Instrument;
+use warp::http::{HeaderMap, Method, StatusCode};
+use warp::{Filter, Rejection};
+
+use crate::auth;
+use crate::backend::{self, Backend, ObjectMeta};
+use crate::config;
+use crate::config::power_config::BucketMode;
+use crate::config::warp_config::WarpConfig;
+use crate::config::webdav_config::WebdavConfig;
+use crate::error::{error_reply, ProxyError};
+use crate::trace;
+use crate::utils::{boxed_body, decode, forwarded_headers, read_body, xml_escape, BodyStream, DEFAULT_MAX_PUT_SIZE};
+use crate::writes::{self, Change, WrittenKey};
+
+// href 中每一段路径只保留非保留字符
+const HREF_ENCODE: &AsciiSet = &NON_ALPHANUMERIC.remove(b'-').remove(b'.').remove(b'_').remove(b'~');
+
+// 请求路径对应的位置
+enum Location {
+    // path 模式下的根目录，列出允许访问的桶
+    Buckets,
+    // 桶内的对象或目录，key 不带首尾的 /
+    Object { bucket: String, key: String },
+}
+
+struct DavRequest {
+    config_key: String,
+    backend: Box<dyn Backend>,
+    webdav: WebdavConfig,
+    // path 模式下第一级目录为桶
+    buckets: Option<Vec<String>>,
+    location: Location,
+    // 路径以 / 结尾时只按目录处理
+    collection: bool,
+}
+
+enum Resource {
+    Collection,
+    File(ObjectMeta),
+}
+
+// PROPFIND 返回的一条资源
+struct Entry {
+    href: String,
+    name: String,
+    collection: bool,
+    size: u64,
+    last_modified: Option<UtcTime>,
+    etag: Option<String>,
+    content_type: Option<String>,
+}
+
+impl DavRequest {
+    // 对象 key 对应的 href，目录以 / 结尾
+    fn href(&self, bucket: Option<&str>, key: &str, collection: bool) -> String {
+        let segments: Vec<String> = std::iter::once(self.config_key.as_str())
+            .chain(bucket.filter(|_| self.buckets.is_some()))
+            .chain(key.split('/').filter(|segment| !segment.is_empty()))
+            .map(|segment| utf8_percent_encode(segment, HREF_ENCODE).to_string())
+            .collect();
+        let mut href = format!("/dav/{}", segments.join("/"));
+        if collection {
+            href.push('/');
+        }
+        href
+    }
+
+    fn object(&self) -> Result<(&str, &str), ProxyError> {
+        match &self.location {
+            Location::Buckets => Err(ProxyError::MethodNotAllowed("The bucket list only supports PROPFIND".to_string())),
+            Location::Object { bucket, key } => Ok((bucket, key)),
+        }
+    }
+
+    // 写入或删除对象后清除缓存并入队复制
+    async fn written(&self, key: &str, change: Change) {
+        if let Location::Object { bucket, .. } = &self.location {
+            writes::written(&self.config_key, bucket, WrittenKey::Stored(key), change).await;
+        }
+    }
+
+    // 复制到目标后删除原对象
+    async fn moved(&self, key: &str, target_key: &str) -> Result<(), ProxyError> {
+        let (bucket, _) = self.object()?;
+        self.backend.copy(bucket, key, target_key).await?;
+        self.written(target_key, Change::Put).await;
+        self.backend.remove(bucket, key).await?;
+        self.written(key, Change::Delete).await;
+        Ok(())
+    }
+}
+
+pub fn routes() -> impl Filter<Extract = (Box<dyn warp::Reply>,), Error = Rejection> + Clone {
+    let body = warp::body::stream().map(boxed_body);
+    warp::path("dav")
+        .and(claim())
+        .and(warp::path::full())
+        .and(warp::method())
+        .and(warp::header::headers_cloned())
+        .and(body)
+        .and(trace::request_id())
+        .and_then(process)
+}
+
+// 只接管开启了 WebDAV 的 power，其余 /dav 请求交给对象路由，按域名路由的 power 中以 dav/ 开头的对象仍然可以读取
+fn claim() -> impl Filter<Extract = (), Error = Rejection> + Clone {
+    warp::path::full()
+        .and(warp::header::optional::<String>("host"))
+        .and_then(|path: warp::path::FullPath, host: Option<String>| async move {
+            if claims(&config::current(), host.as_deref(), path.as_str()) {
+                Ok(())
+            } else {
+                Err(warp::reject::not_found())
+            }
+        })
+        .untuple_one()
+}
+
+// Host 匹配到 power 时只接管该 power 自己的 WebDAV 路径
+fn claims(config: &WarpConfig, host: Option<&str>, raw_path: &str) -> bool {
+    let path = raw_path.strip_prefix("/dav/").unwrap_or_default();
+    let config_key = decode(path.split('/').next().unwrap_or_default());
+    let enabled = config.power_config(&config_key).is_some_and(|power| power.webdav.is_some());
+    enabled && host.and_then(|host| config.power_by_host(host)).is_none_or(|power| power == config_key)
+}
+
+async fn process(
+    path: warp::path::FullPath,
+    method: Method,
+    headers: HeaderMap,
+    body: BodyStream,
+    request_id: String,
+) -> Result<Box<dyn warp::Reply>, Rejection> {
+    let span = tracing::info_span!(
+        "dav_request",
+        request_id = %request_id,
+        method = %method,
+        path = %path.as_str(),
+    );
+    let reply = match handle(path.as_str(), &method, &headers, body).instrument(span).await {
+        Ok(reply) => reply,
+        Err(e) => error_reply(e, &request_id)?,
+    };
+    Ok(Box::new(warp::reply::with_header(reply, config::REQUEST_ID_HEADER, request_id)))
+}
+
+async fn handle(
+    raw_path: &str,
+    method: &Method,
+    headers: &HeaderMap,
+    body: BodyStream,
+) -> Result<Box<dyn warp::Reply>, ProxyError> {
+    let config = config::current();
+    let path = raw_path.strip_prefix("/dav/").unwrap_or_default();
+    let (config_key, rest) = path.split_once('/').unwrap_or((path, ""));
+    let config_key = decode(config_key);
+    let request = parse(&config, &config_key, rest)?;
+    if !auth::check(headers.clone(), &config_key).await {
+        return Err(ProxyError::Unauthorized);
+    }
+
+    log::info!("DAV access: {} {}", method, raw_path);
+
+    let method = method.as_str();
+    if matches!(method, "PUT" | "DELETE" | "MKCOL" | "MOVE") && !request.webdav.writable {
+        return Err(ProxyError::MethodNotAllowed(format!("WebDAV is read-only for {}", config_key)));
+    }
+    match method {
+        "OPTIONS" => Ok(options(&request.webdav)),
+        "PROPFIND" => propfind(&request, headers).await,
+        "GET" => get(&request, headers).await,
+        "HEAD" => head(&request).await,
+        "PUT" => put(&request, headers, body).await,
+        "DELETE" => delete(&request).await,
+        "MKCOL" => mkcol(&request).await,
+        "MOVE" => move_to(&config, &request, headers).await,
+        other => Err(ProxyError::MethodNotAllowed(format!("{} is not supported", other))),
+    }
+}
+
+// 解析 /dav/{config_key}/ 之后的路径，path 模式下第一段为桶
+fn parse(config: &WarpConfig, config_key: &str, rest: &str) -> Result<DavRequest, ProxyError> {
+    let power = config
+        .power_config(config_key)
+        .ok_or_else(|| ProxyError::UnknownConfigKey(config_key.to_string()))?;
+    let webdav = power
+        .webdav
+        .clone()
+        .ok_or_else(|| ProxyError::NotFound(format!("WebDAV is not enabled for {}", config_key)))?;
+
+    let collection = rest.is_empty() || rest.ends_with('/');
+    let rest = decode(rest.trim_matches('/'));
+    let (buckets, location) = match power.bucket_mode {
+        BucketMode::Path => {
+            let buckets = power.buckets.clone().unwrap_or_default();
+            let location = if rest.is_empty() {
+                Location::Buckets
+            } else {
+                let (bucket, key) = rest.split_once('/').unwrap_or((&rest, ""));
+                if !buckets.iter().any(|allowed| allowed == bucket) {
+                    return Err(ProxyError::AccessDenied(format!("Bucket {} is not allowed for {}", bucket, config_key)));
+                }
+                Location::Object { bucket: bucket.to_string(), key: key.to_string() }
+            };
+            (Some(buckets), location)
+        }
+        BucketMode::Fixed => {
+            // fs 后端在 fixed 模式下不使用桶名
+            let bucket = config
+                .bucket_name(config_key.to_string())
+                .or_else(|| config.default.bucket_name.clone())
+                .unwrap_or_default();
+            (None, Location::Object { bucket, key: rest })
+        }
+    };
+
+    Ok(DavRequest {
+        config_key: config_key.to_string(),
+        backend: backend::for_config(config, config_key)?,
+        webdav,
+        buckets,
+        location,
+        collection,
+    })
+}
+
+// 桶内没有目录，key 为对象时按文件处理，否则存在以 key/ 开头的对象时按目录处理
+async fn resolve(request: &DavRequest) -> Result<Resource, ProxyError> {
+    let (bucket, key) = match &request.location {
+        Location::Buckets => return Ok(Resource::Collection),
+        Location::Object { key, .. } if key.is_empty() => return Ok(Resource::Collection),
+        Location::Object { bucket, key } => (bucket, key),
+    };
+    if !request.collection {
+        match request.backend.stat(bucket, key).await {
+            Ok(meta) => return Ok(Resource::File(meta)),
+            Err(ProxyError::NoSuchKey(_)) => {}
+            Err(e) => return Err(e),
+        }
+    }
+    if request.backend.prefix_exists(bucket, &format!("{}/", key)).await? {
+        Ok(Resource::Collection)
+    } else {
+        Err(ProxyError::NoSuchKey(key.clone()))
+    }
+}
+
+fn options(webdav: &WebdavConfig) -> Box<dyn warp::Reply> {
+    let allow = if webdav.writable {
+        "OPTIONS, PROPFIND, GET, HEAD, PUT, DELETE, MKCOL, MOVE"
+    } else {
+        "OPTIONS, PROPFIND, GET, HEAD"
+    };
+    let reply = warp::reply::with_header(StatusCode::OK, "DAV", "1");
+    let reply = warp::reply::with_header(reply, "Allow", allow);
+    Box::new(warp::reply::with_header(reply, "MS-Author-Via", "DAV"))
+}
+
+async fn propfind(request: &DavRequest, headers: &HeaderMap) -> Result<Box<dyn warp::Reply>, ProxyError> {
+    // 未指定时按 1 处理；不支持 Depth: infinity，按 RFC 4918 返回 propfind-finite-depth 前置条件
+    let depth = match headers.get("depth").and_then(|v| v.to_str().ok()) {
+        Some("0") => 0,
+        Some("1") | None => 1,
+        Some(depth) if depth.eq_ignore_ascii_case("infinity") => return Ok(finite_depth_required()),
+        Some(depth) => return Err(ProxyError::BadRequest(format!("Invalid Depth header `{}`", depth))),
+    };
+
+    let mut entries = Vec::new();
+    match (&request.location, resolve(request).await?) {
+        (Location::Object { bucket, key }, Resource::File(meta)) => {
+            entries.push(Entry {
+                href: request.href(Some(bucket), key, false),
+                name: key.rsplit('/').next().unwrap_or_default().to_string(),
+                collection: false,
+                size: meta.size,
+                last_modified: meta.last_modified,
+                etag: Some(meta.etag),
+                content_type: meta.content_type,
+            });
+        }
+        (Location::Buckets, _) => {
+            entries.push(collection_entry(request.href(None, "", true), &request.config_key));
+            if depth == 1 {
+                for bucket in request.buckets.iter().flatten() {
+                    entries.push(collection_entry(request.href(Some(bucket), "", true), bucket));
+                }
+            }
+        }
+        (Location::Object { bucket, key }, Resource::Collection) => {
+            let name = key.rsplit('/').next().filter(|name| !name.is_empty()).unwrap_or(bucket);
+            entries.push(collection_entry(request.href(Some(bucket), key, true), name));
+            if depth == 1 {
+                entries.extend(children(request, bucket, key).await?);
+            }
+        }
+    }
+    Ok(multistatus(&entries))
+}
+
+fn finite_depth_required() -> Box<dyn warp::Reply> {
+    let xml = "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n<D:error xmlns:D=\"DAV:\"><D:propfind-finite-depth/></D:error>";
+    let response = warp::http::Response::builder()
+        .status(StatusCode::FORBIDDEN)
+        .header("Content-Type", "application/xml; charset=utf-8")
+        .body(xml)
+        .unwrap();
+    Box::new(response)
+}
+
+// 目录下一级的对象和子目录
+async fn children(request: &DavRequest, bucket: &str, key: &str) -> Result<Vec<Entry>, ProxyError> {
+    let prefix = if key.is_empty() { String::new() } else { format!("{}/", key) };
+    let items = request.backend.list(bucket, &prefix, Some("/")).await?;
+    Ok(items
+        .into_iter()
+        // MKCOL 创建的目录占位对象
+        .filter(|item| item.name != prefix)
+        .map(|item| {
+            let path = item.name.trim_end_matches('/');
+            let name = path[prefix.len().min(path.len())..].to_string();
+            if item.is_prefix {
+                return collection_entry(request.href(Some(bucket), path, true), &name);
+            }
+            Entry {
+                href: request.href(Some(bucket), path, false),
+                content_type: Some(from_path(&name).first_or_octet_stream().to_string()),
+                name,
+                collection: false,
+                size: item.size,
+                last_modified: item.last_modified,
+                etag: item.etag,
+            }
+        })
+        .collect())
+}
+
+fn collection_entry(href: String, name: &str) -> Entry {
+    Entry {
+        href,
+        name: name.to_string(),
+        collection: true,
+        size: 0,
+        last_modified: None,
+        etag: None,
+        content_type: None,
+    }
+}
+
+fn multistatus(entries: &[Entry]) -> Box<dyn warp::Reply> {
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n<D:multistatus xmlns:D=\"DAV:\">");
+    for entry in entries {
+        xml.push_str(&format!("<D:response><D:href>{}</D:href><D:propstat><D:prop>", xml_escape(&entry.href)));
+        xml.push_str(&format!("<D:displayname>{}</D:displayname>", xml_escape(&entry.name)));
+        if entry.collection {
+            xml.push_str("<D:resourcetype><D:collection/></D:resourcetype>");
+        } else {
+            xml.push_str("<D:resourcetype/>");
+            xml.push_str(&format!("<D:getcontentlength>{}</D:getcontentlength>", entry.size));
+        }
+        if let Some(last_modified) = entry.last_modified {
+            xml.push_str(&format!("<D:getlastmodified>{}</D:getlastmodified>", to_http_header_value(last_modified)));
+        }
+        if let Some(etag) = &entry.etag {
+            xml.push_str(&format!("<D:getetag>\"{}\"</D:getetag>", xml_escape(etag.trim_matches('"'))));
+        }
+        if let Some(content_type) = &entry.content_type {
+            xml.push_str(&format!("<D:getcontenttype>{}</D:getcontenttype>", xml_escape(content_type)));
+        }
+        xml.push_str("</D:prop><D:status>HTTP/1.1 200 OK</D:status></D:propstat></D:response>");
+    }
+    xml.push_str("</D:multistatus>");
+
+    let response = warp::http::Response::builder()
+        .status(StatusCode::MULTI_STATUS)
+        .header("Content-Type", "application/xml; charset=utf-8")
+        .body(xml)
+        .unwrap();
+    Box::new(response)
+}
+
+async fn get(request: &DavRequest, headers: &HeaderMap) -> Result<Box<dyn warp::Reply>, ProxyError> {
+    let (bucket, key) = request.object()?;
+    if request.collection || key.is_empty() {
+        return Err(ProxyError::MethodNotAllowed("GET on a collection is not supported".to_string()));
+    }
+
+    // WebDAV 展示的是存储中的实际 key，不应用改写规则
+    let object = request.backend.get(bucket, key, headers).await?;
+    let mut reply = warp::http::Response::new(object.body);
+    *reply.status_mut() = object.status;
+    *reply.headers_mut() = forwarded_headers(&object.headers);
+    Ok(Box::new(reply))
+}
+
+async fn head(request: &DavRequest) -> Result<Box<dyn warp::Reply>, ProxyError> {
+    let meta = match resolve(request).await? {
+        Resource::Collection => return Ok(Box::new(StatusCode::OK)),
+        Resource::File(meta) => meta,
+    };
+    let mut reply = warp::http::Response::new(warp::hyper::Body::empty());
+    *reply.headers_mut() = meta.headers();
+    Ok(Box::new(reply))
+}
+
+async fn put(request: &DavRequest, headers: &HeaderMap, body: BodyStream) -> Result<Box<dyn warp::Reply>, ProxyError> {
+    let (bucket, key) = request.object()?;
+    if request.collection || key.is_empty() {
+        return Err(ProxyError::MethodNotAllowed("PUT on a collection is not allowed".to_string()));
+    }
+
+    let limit = request.webdav.max_put_size.unwrap_or(DEFAULT_MAX_PUT_SIZE);
+    let body = read_body(body, headers, limit).await?;
+    let content_type = headers
+        .get("content-type")
+        .and_then(|v| v.to_str().ok())
+        .map(str::to_string)
+        .unwrap_or_else(|| from_path(key).first_or_octet_stream().to_string());
+    let mut put_headers = Multimap::new();
+    put_headers.insert("Content-Type".to_string(), content_type);
+
+    let etag = request.backend.put(bucket, key, body, put_headers).await?;
+    request.written(key, Change::Put).await;
+    Ok(Box::new(warp::reply::with_header(StatusCode::CREATED, "ETag", format!("\"{}\"", etag))))
+}
+
+async fn delete(request: &DavRequest) -> Result<Box<dyn warp::Reply>, ProxyError> {
+    let (bucket, key) = request.object()?;
+    if key.is_empty() {
+        return Err(ProxyError::AccessDenied("The bucket root cannot be deleted".to_string()));
+    }
+    // 每删除一个对象立即清除缓存，中途失败时已删除的对象不会留在缓存中
+    match resolve(request).await? {
+        Resource::File(_) => {
+            request.backend.remove(bucket, key).await?;
+            request.written(key, Change::Delete).await;
+        }
+        Resource::Collection => {
+            for item in request.backend.list(bucket, &format!("{}/", key), None).await? {
+                request.backend.remove(bucket, &item.name).await?;
+                request.written(&item.name, Change::Delete).await;
+            }
+        }
+    }
+    Ok(Box::new(StatusCode::NO_CONTENT))
+}
+
+// 对象存储没有目录，MKCOL 写入一个以 / 结尾的空对象作为占位
+async fn mkcol(request: &DavRequest) -> Result<Box<dyn warp::Reply>, ProxyError> {
+    let (bucket, key) = request.object()?;
+    match resolve(request).await {
+        Ok(_) => return Err(ProxyError::MethodNotAllowed(format!("{} already exists", key))),
+        Err(ProxyError::NoSuchKey(_)) => {}
+        Err(e) => return Err(e),
+    }
+    let placeholder = format!("{}/", key);
+    request.backend.put(bucket, &placeholder, Bytes::new(), Multimap::new()).await?;
+    request.written(&placeholder, Change::Put).await;
+    Ok(Box::new(StatusCode::CREATED))
+}
+
+// 同一个桶内移动对象或目录，复制后删除原对象
+async fn move_to(config: &WarpConfig, request: &DavRequest, headers: &HeaderMap) -> Result<Box<dyn warp::Reply>, ProxyError> {
+    let (bucket, key) = request.object()?;
+    if key.is_empty() {
+        return Err(ProxyError::AccessDenied("The bucket root cannot be moved".to_string()));
+    }
+
+    let destination = headers
+        .get("destination")
+        .and_then(|v| v.to_str().ok())
+        .ok_or_else(|| ProxyError::BadRequest("Missing Destination header".to_string()))?;
+    // Destination 可以是完整 URL 或绝对路径
+    let destination_path = match url::Url::parse(destination) {
+        Ok(url) => url.path().to_string(),
+        Err(_) => destination.to_string(),
+    };
+    let base = format!("/dav/{}/", utf8_percent_encode(&request.config_key, HREF_ENCODE));
+    let rest = destination_path
+        .strip_prefix(&base)
+        .ok_or_else(|| ProxyError::BadRequest("Destination must be in the same power".to_string()))?;
+    let target = parse(config, &request.config_key, rest)?;
+    let (target_bucket, target_key) = target.object()?;
+    if target_bucket != bucket || target_key.is_empty() {
+        return Err(ProxyError::BadRequest("Destination must be an object in the same bucket".to_string()));
+    }
+    if target_key == key || target_key.starts_with(&format!("{}/", key)) {
+        return Err(ProxyError::BadRequest("Destination must not be the source or inside it".to_string()));
+    }
+
+    let resource = resolve(request).await?;
+    let exists = match resolve(&target).await {
+        Ok(_) => true,
+        Err(ProxyError::NoSuchKey(_)) => false,
+        Err(e) => return Err(e),
+    };
+    let overwrite = headers.get("overwrite").and_then(|v| v.to_str().ok()) != Some("F");
+    if exists && !overwrite {
+        return Err(ProxyError::PreconditionFailed(format!("{} already exists", target_key)));
+    }
+
+    // 源和目标逐个对象处理，中途失败时已移动的对象同样清除缓存并复制
+    match resource {
+        Resource::File(_) => request.moved(key, target_key).await?,
+        Resource::Collection => {
+            let prefix = format!("{}/", key);
+            for item in request.backend.list(bucket, &prefix, None).await? {
+                let target_name = format!("{}/{}", target_key, &item.name[prefix.len()..]);
+                request.moved(&item.name, &target_name).await?;
+            }
+        }
+    }
+    Ok(Box::new(if exists { StatusCode::NO_CONTENT } else { StatusCode::CREATED }))
+}
+
+#[cfg(test)]
+mod tests {
+    use warp::Reply;
+
+    use super::*;
+
+    const CONFIG: &str = "
+power:
+  files: {backend: fs, root: /tmp, bucket-name: files, webdav: {}}
+  multi: {backend: fs, root: /tmp, bucket-mode: path, buckets: [bkt-a, bkt-b], webdav: {writable: true}}
+  site: {backend: fs, root: /tmp, hosts: [site.a.com], webdav: {}}
+  plain: {backend: fs, root: /tmp, hosts: ['*.plain.com']}
+";
+
+    fn config() -> WarpConfig {
+        serde_yaml::from_str(CONFIG).unwrap()
+    }
+
+    fn object(request: &DavRequest) -> (&str, &str) {
+        request.object().expect("location should be an object")
+    }
+
+    #[test]
+    fn path_mode_checks_bucket_allowlist() {
+        let config = config();
+        let request = parse(&config, "multi", "bkt-b/dir/a.txt").unwrap();
+        assert_eq!(object(&request), ("bkt-b", "dir/a.txt"));
+        assert!(!request.collection);
+        assert!(matches!(parse(&config, "multi", "").unwrap().location, Location::Buckets));
+        assert!(parse(&config, "multi", "bkt-a/").unwrap().collection);
+        assert!(matches!(parse(&config, "multi", "bkt-c/a.txt"), Err(ProxyError::AccessDenied(_))));
+        assert!(matches!(parse(&config, "plain", "a.txt"), Err(ProxyError::NotFound(_))));
+        assert!(matches!(parse(&config, "missing", "a.txt"), Err(ProxyError::UnknownConfigKey(_))));
+    }
+
+    #[test]
+    fn encoded_segments_are_decoded_once() {
+        let config = config();
+        let request = parse(&config, "files", "%E6%96%87%E6%A1%A3/%E6%8A%A5%E5%91%8A%2520v1.pdf").unwrap();
+        assert_eq!(object(&request), ("files", "文档/报告%20v1.pdf"));
+        assert_eq!(
+            request.href(Some("files"), "文档/报告%20v1.pdf", false),
+            "/dav/files/%E6%96%87%E6%A1%A3/%E6%8A%A5%E5%91%8A%2520v1.pdf"
+        );
+        assert_eq!(request.href(Some("files"), "文档", true), "/dav/files/%E6%96%87%E6%A1%A3/");
+    }
+
+    #[test]
+    fn href_includes_bucket_in_path_mode() {
+        let config = config();
+        let request = parse(&config, "multi", "").unwrap();
+        assert_eq!(request.href(None, "", true), "/dav/multi/");
+        assert_eq!(request.href(Some("bkt-a"), "", true), "/dav/multi/bkt-a/");
+        assert_eq!(request.href(Some("bkt-a"), "a b/c#d.txt", false), "/dav/multi/bkt-a/a%20b/c%23d.txt");
+    }
+
+    #[test]
+    fn claims_respect_host_routing() {
+        let config = config();
+        // 没有匹配的 Host 时按路径中的 power 接管
+        assert!(claims(&config, None, "/dav/files/a.txt"));
+        assert!(claims(&config, Some("other.com"), "/dav/site/"));
+        // Host 匹配到的 power 只接管自己的 WebDAV 路径
+        assert!(claims(&config, Some("site.a.com:8080"), "/dav/site/a.txt"));
+        assert!(!claims(&config, Some("site.a.com"), "/dav/files/a.txt"));
+        // 未开启 WebDAV 的 power 中 dav/ 开头的路径按对象读取
+        assert!(!claims(&config, Some("x.plain.com"), "/dav/plain/a.txt"));
+        assert!(!claims(&config, None, "/dav/plain/a.txt"));
+        assert!(!claims(&config, None, "/dav/missing/"));
+    }
+
+    #[tokio::test]
+    async fn infinite_depth_returns_precondition() {
+        let config = config();
+        let request = parse(&config, "files", "").unwrap();
+        let mut headers = HeaderMap::new();
+        headers.insert("depth", "infinity".parse().unwrap());
+        let Ok(reply) = propfind(&request, &headers).await else { panic!("PROPFIND should reply") };
+        let response = reply.into_response();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        let body = warp::hyper::body::to_bytes(response.into_body()).await.unwrap();
+        assert!(String::from_utf8_lossy(&body).contains("<D:error xmlns:D=\"DAV:\"><D:propfind-finite-depth/></D:error>"));
+
+        headers.insert("depth", "2".parse().unwrap());
+        assert!(matches!(propfind(&request, &headers).await, Err(ProxyError::BadRequest(_))));
+    }
+}
@@ -0,0 +1,145 @@
+use std::collections::HashMap;
+
+use percent_encoding::{percent_decode_str, utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+use warp::http::HeaderMap;
+
+use crate::utils::decode;
+
+// 上传时保存原始文件名的元数据
+const ORIGINAL_NAME_META: &str = "x-amz-meta-original-name";
+
+// 文件名最大字符数，超出部分截断
+const MAX_FILENAME_CHARS: usize = 200;
+
+// RFC 5987 attr-char 之外的字符都需要编码
+const ATTR_CHAR: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'!')
+    .remove(b'#')
+    .remove(b'$')
+    .remove(b'&')
+    .remove(b'+')
+    .remove(b'-')
+    .remove(b'.')
+    .remove(b'^')
+    .remove(b'_')
+    .remove(b'`')
+    .remove(b'|')
+    .remove(b'~');
+
+// 根据 filename、disposition 参数生成 Content-Disposition，两者都未传时沿用上游的值
+pub fn content_disposition(
+    params: &HashMap<String, String>,
+    upstream_headers: &HeaderMap,
+    object_key: &str,
+) -> Option<String> {
+    let filename = params.get("filename");
+    let disposition = match (params.get("disposition"), filename) {
+        (Some(d), _) if d.eq_ignore_ascii_case("inline") => "inline",
+        (Some(_), _) | (None, Some(_)) => "attachment",
+        (None, None) => return None,
+    };
+
+    let filename = filename
+        .cloned()
+        .or_else(|| original_name(upstream_headers))
+        // 请求路径中的 key 仍是百分号编码的，解码后再处理，避免中文文件名被重复编码
+        .unwrap_or_else(|| decode(object_key.rsplit('/').next().unwrap_or("")));
+    let filename = sanitize_filename(&filename);
+
+    if filename.is_empty() {
+        return Some(disposition.to_string());
+    }
+    Some(format_disposition(disposition, &filename))
+}
+
+// RFC 6266: ASCII 回退的 filename 和 RFC 5987 编码的 filename*
+fn format_disposition(disposition: &str, filename: &str) -> String {
+    let fallback = ascii_fallback(filename);
+    if fallback == filename {
+        return format!("{}; filename=\"{}\"", disposition, fallback);
+    }
+    format!(
+        "{}; filename=\"{}\"; filename*=UTF-8''{}",
+        disposition,
+        fallback,
+        utf8_percent_encode(filename, ATTR_CHAR),
+    )
+}
+
+fn original_name(headers: &HeaderMap) -> Option<String> {
+    let value = headers.get(ORIGINAL_NAME_META)?.to_str().ok()?;
+    let decoded = percent_decode_str(value).decode_utf8().ok()?.to_string();
+    if decoded.trim().is_empty() {
+        None
+    } else {
+        Some(decoded)
+    }
+}
+
+// 去除控制字符、路径分隔符和引号，防止响应头注入和路径穿越
+fn sanitize_filename(filename: &str) -> String {
+    let sanitized: String = filename
+        .chars()
+        .filter(|c| !c.is_control())
+        .map(|c| match c {
+            '/' | '\\' | '"' | ';' => '_',
+            c => c,
+        })
+        .take(MAX_FILENAME_CHARS)
+        .collect();
+    sanitized.trim().trim_matches('.').to_string()
+}
+
+// 非 ASCII 字符替换为下划线，保留扩展名以便旧客户端识别文件类型
+fn ascii_fallback(filename: &str) -> String {
+    filename
+        .chars()
+        .map(|c| if c.is_ascii() && !c.is_ascii_control() && c != '%' { c } else { '_' })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn disposition(params: &[(&str, &str)], object_key: &str) -> Option<String> {
+        let params = params.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect();
+        content_disposition(&params, &HeaderMap::new(), object_key)
+    }
+
+    #[test]
+    fn chinese_object_key_is_decoded_once() {
+        let value = disposition(&[("disposition", "attachment")], "docs/%E6%8A%A5%E5%91%8A.pdf").unwrap();
+        assert_eq!(value, "attachment; filename=\"__.pdf\"; filename*=UTF-8''%E6%8A%A5%E5%91%8A.pdf");
+    }
+
+    #[test]
+    fn quotes_and_semicolons_are_replaced() {
+        let value = disposition(&[("filename", "a\"b;c.txt")], "x.txt").unwrap();
+        assert_eq!(value, "attachment; filename=\"a_b_c.txt\"");
+    }
+
+    #[test]
+    fn line_breaks_in_filename_are_removed() {
+        let value = disposition(&[("filename", "a.txt\r\nSet-Cookie: x=1")], "x.txt").unwrap();
+        assert!(!value.contains('\r') && !value.contains('\n'), "{}", value);
+        assert_eq!(value, "attachment; filename=\"a.txtSet-Cookie: x=1\"");
+    }
+
+    #[test]
+    fn inline_or_attachment() {
+        assert_eq!(disposition(&[], "a.txt"), None);
+        assert_eq!(disposition(&[("disposition", "INLINE")], "dir/a.txt").unwrap(), "inline; filename=\"a.txt\"");
+        assert_eq!(disposition(&[("disposition", "other")], "a.txt").unwrap(), "attachment; filename=\"a.txt\"");
+        assert_eq!(disposition(&[("filename", "b.txt")], "a.txt").unwrap(), "attachment; filename=\"b.txt\"");
+    }
+
+    #[test]
+    fn original_name_metadata_is_used() {
+        let mut headers = HeaderMap::new();
+        headers.insert(ORIGINAL_NAME_META, "%E6%8A%A5%E5%91%8A.pdf".parse().unwrap());
+        let params = HashMap::from([("disposition".to_string(), "inline".to_string())]);
+        let value = content_disposition(&params, &headers, "abc123").unwrap();
+        assert_eq!(value, "inline; filename=\"__.pdf\"; filename*=UTF-8''%E6%8A%A5%E5%91%8A.pdf");
+    }
+}
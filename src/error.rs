@@ -0,0 +1,179 @@
+use std::fmt;
+
+use reqwest::StatusCode;
+use warp::Rejection;
+
+// 统一的错误类型，对应响应状态码和 JSON 错误体中的 code 字段
+#[derive(Debug, Clone)]
+pub enum ProxyError {
+    // 请求路径不符合 match-prefix
+    BadRequest(String),
+    Unauthorized,
+    // 未知的接口路径
+    NotFound(String),
+    // 未配置的 config_key
+    UnknownConfigKey(String),
+    NoSuchKey(String),
+    NoSuchBucket(String),
+    AccessDenied(String),
+    InvalidRange(String),
+    // 资源不支持该请求方法，如只读的 WebDAV
+    MethodNotAllowed(String),
+    PreconditionFailed(String),
+    PayloadTooLarge(String),
+    // 没有健康的 MinIO 实例或连接池获取失败
+    PoolUnavailable(String),
+    Timeout(String),
+    // 上游返回了无法识别的错误
+    Upstream(String),
+    Config(String),
+}
+
+impl ProxyError {
+    pub fn status(&self) -> StatusCode {
+        match self {
+            ProxyError::BadRequest(_) => StatusCode::BAD_REQUEST,
+            ProxyError::Unauthorized => StatusCode::UNAUTHORIZED,
+            ProxyError::NotFound(_) => StatusCode::NOT_FOUND,
+            ProxyError::UnknownConfigKey(_) => StatusCode::NOT_FOUND,
+            ProxyError::NoSuchKey(_) => StatusCode::NOT_FOUND,
+            ProxyError::NoSuchBucket(_) => StatusCode::NOT_FOUND,
+            ProxyError::AccessDenied(_) => StatusCode::FORBIDDEN,
+            ProxyError::InvalidRange(_) => StatusCode::RANGE_NOT_SATISFIABLE,
+            ProxyError::MethodNotAllowed(_) => StatusCode::METHOD_NOT_ALLOWED,
+            ProxyError::PreconditionFailed(_) => StatusCode::PRECONDITION_FAILED,
+            ProxyError::PayloadTooLarge(_) => StatusCode::PAYLOAD_TOO_LARGE,
+            ProxyError::PoolUnavailable(_) => StatusCode::SERVICE_UNAVAILABLE,
+            ProxyError::Timeout(_) => StatusCode::GATEWAY_TIMEOUT,
+            ProxyError::Upstream(_) => StatusCode::BAD_GATEWAY,
+            ProxyError::Config(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    pub fn code(&self) -> &'static str {
+        match self {
+            ProxyError::BadRequest(_) => "BadRequest",
+            ProxyError::Unauthorized => "Unauthorized",
+            ProxyError::NotFound(_) => "NotFound",
+            ProxyError::UnknownConfigKey(_) => "UnknownConfigKey",
+            ProxyError::NoSuchKey(_) => "NoSuchKey",
+            ProxyError::NoSuchBucket(_) => "NoSuchBucket",
+            ProxyError::AccessDenied(_) => "AccessDenied",
+            ProxyError::InvalidRange(_) => "InvalidRange",
+            ProxyError::MethodNotAllowed(_) => "MethodNotAllowed",
+            ProxyError::PreconditionFailed(_) => "PreconditionFailed",
+            ProxyError::PayloadTooLarge(_) => "EntityTooLarge",
+            ProxyError::PoolUnavailable(_) => "ServiceUnavailable",
+            ProxyError::Timeout(_) => "GatewayTimeout",
+            ProxyError::Upstream(_) => "BadGateway",
+            ProxyError::Config(_) => "ConfigError",
+        }
+    }
+
+    // 根据 S3 错误码转换，未知错误码按上游 HTTP 状态码归类
+    pub fn from_s3_code(code: &str, message: String, status: Option<StatusCode>) -> Self {
+        match code {
+            "NoSuchKey" => ProxyError::NoSuchKey(message),
+            "NoSuchBucket" => ProxyError::NoSuchBucket(message),
+            "AccessDenied" | "InvalidAccessKeyId" | "SignatureDoesNotMatch" => ProxyError::AccessDenied(message),
+            "InvalidRange" => ProxyError::InvalidRange(message),
+            "PreconditionFailed" => ProxyError::PreconditionFailed(message),
+            "RequestTimeout" => ProxyError::Timeout(message),
+            "SlowDown" | "ServiceUnavailable" => ProxyError::PoolUnavailable(message),
+            _ => match status {
+                Some(StatusCode::NOT_FOUND) => ProxyError::NoSuchKey(message),
+                Some(StatusCode::FORBIDDEN) => ProxyError::AccessDenied(message),
+                Some(StatusCode::SERVICE_UNAVAILABLE) => ProxyError::PoolUnavailable(message),
+                Some(StatusCode::GATEWAY_TIMEOUT) => ProxyError::Timeout(message),
+                _ => ProxyError::Upstream(format!("{} {}", code, message)),
+            },
+        }
+    }
+
+    // 解析上游返回的 S3 XML 错误体
+    pub fn from_upstream_response(status: StatusCode, body: &str) -> Self {
+        let code = xml_tag(body, "Code").unwrap_or_default();
+        let message = xml_tag(body, "Message")
+            .unwrap_or_else(|| status.canonical_reason().unwrap_or("").to_string());
+        ProxyError::from_s3_code(&code, message, Some(status))
+    }
+}
+
+impl fmt::Display for ProxyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProxyError::Unauthorized => write!(f, "Unauthorized"),
+            ProxyError::UnknownConfigKey(key) => write!(f, "Unknown config key: {}", key),
+            ProxyError::BadRequest(msg)
+            | ProxyError::NotFound(msg)
+            | ProxyError::NoSuchKey(msg)
+            | ProxyError::NoSuchBucket(msg)
+            | ProxyError::AccessDenied(msg)
+            | ProxyError::InvalidRange(msg)
+            | ProxyError::MethodNotAllowed(msg)
+            | ProxyError::PreconditionFailed(msg)
+            | ProxyError::PayloadTooLarge(msg)
+            | ProxyError::PoolUnavailable(msg)
+            | ProxyError::Timeout(msg)
+            | ProxyError::Upstream(msg)
+            | ProxyError::Config(msg) => write!(f, "{}", msg),
+        }
+    }
+}
+
+impl From<minio::s3::error::Error> for ProxyError {
+    fn from(e: minio::s3::error::Error) -> Self {
+        use minio::s3::error::Error;
+        match e {
+            Error::S3Error(resp) => ProxyError::from_s3_code(&resp.code, resp.message, None),
+            Error::HttpError(e) => e.into(),
+            Error::ServerError(status) => {
+                let status = StatusCode::from_u16(status).unwrap_or(StatusCode::BAD_GATEWAY);
+                ProxyError::from_s3_code("", format!("MinIO server error: {}", status), Some(status))
+            }
+            Error::InvalidObjectName(msg) | Error::InvalidBucketName(msg) => ProxyError::BadRequest(msg),
+            Error::InvalidBaseUrl(msg) | Error::UrlBuildError(msg) => ProxyError::Config(msg),
+            e => ProxyError::Upstream(e.to_string()),
+        }
+    }
+}
+
+impl From<reqwest::Error> for ProxyError {
+    fn from(e: reqwest::Error) -> Self {
+        if e.is_timeout() {
+            ProxyError::Timeout(e.to_string())
+        } else if e.is_connect() {
+            ProxyError::PoolUnavailable(e.to_string())
+        } else {
+            ProxyError::Upstream(e.to_string())
+        }
+    }
+}
+
+pub fn error_reply(error: ProxyError, request_id: &str) -> Result<Box<dyn warp::Reply>, Rejection> {
+    let status_code = error.status();
+    if status_code.is_server_error() {
+        log::error!("Request failed: {} {}", error.code(), error);
+    } else {
+        log::info!("Request rejected: {} {}", error.code(), error);
+    }
+    let body = serde_json::json!({
+        "code": error.code(),
+        "message": error.to_string(),
+        "request_id": request_id,
+    });
+    let response = warp::http::Response::builder()
+        .status(status_code)
+        .header("Content-Type", "application/json")
+        .body(body.to_string())
+        .unwrap();
+    Ok(Box::new(response) as Box<dyn warp::Reply>)
+}
+
+fn xml_tag(body: &str, tag: &str) -> Option<String> {
+    let open = format!("<{}>", tag);
+    let close = format!("</{}>", tag);
+    let start = body.find(&open)? + open.len();
+    let end = body[start..].find(&close)? + start;
+    Some(body[start..end].to_string())
+}
@@ -0,0 +1,124 @@
+use mime_guess::from_path;
+use warp::http::{HeaderMap, StatusCode};
+
+use crate::backend;
+use crate::config::fallback_config::FallbackConfig;
+use crate::config;
+use crate::error::ProxyError;
+use crate::headers;
+use crate::minio;
+use crate::routing::Route;
+use crate::utils::xml_escape;
+
+type Response = warp::http::Response<warp::hyper::Body>;
+
+// 根据 power 中配置的兜底规则生成响应，未配置或兜底本身失败时返回 None
+pub async fn fallback_reply(route: &Route, error: &ProxyError, request_id: &str) -> Option<Box<dyn warp::Reply>> {
+    let config_key = route.config_key.as_str();
+    let status = error.status();
+    let config = config::current();
+    let fallback = config
+        .power_config(config_key)?
+        .fallback
+        .as_ref()?
+        .get(&status.as_u16())?;
+
+    let reply_status = fallback
+        .status
+        .and_then(|code| StatusCode::from_u16(code).ok())
+        .unwrap_or(status);
+
+    let reply = if let Some(object) = &fallback.object {
+        object_reply(config_key, route.bucket.as_deref(), object, fallback, reply_status).await
+    } else if let Some(file) = &fallback.file {
+        file_reply(file, fallback, reply_status).await
+    } else {
+        fallback
+            .template
+            .as_ref()
+            .and_then(|template| template_reply(template, fallback, reply_status, error, request_id))
+    };
+
+    match reply {
+        Some(mut reply) => {
+            // 与正常响应一样按请求路径应用响应头策略
+            headers::apply_header_policy(config_key, &route.object_key, reply.headers_mut());
+            Some(Box::new(reply))
+        }
+        None => {
+            log::warn!("Fallback for status {} of {} failed, using default error reply", status, config_key);
+            None
+        }
+    }
+}
+
+// path 模式下从请求路径中的桶读取兜底对象
+async fn object_reply(
+    config_key: &str,
+    route_bucket: Option<&str>,
+    object: &str,
+    fallback: &FallbackConfig,
+    status: StatusCode,
+) -> Option<Response> {
+    let bucket = minio::minio_parser::bucket_for(config_key, route_bucket).await;
+    let response = backend::for_power(config_key)
+        .ok()?
+        .get(&bucket, object, &HeaderMap::new())
+        .await
+        .ok()?;
+    if !response.status.is_success() {
+        return None;
+    }
+
+    let content_type = fallback.content_type.clone().unwrap_or_else(|| {
+        response
+            .headers
+            .get("Content-Type")
+            .and_then(|v| v.to_str().ok())
+            .map(|v| v.to_string())
+            .unwrap_or_else(|| from_path(object).first_or_octet_stream().to_string())
+    });
+    build_reply(status, &content_type, response.body)
+}
+
+async fn file_reply(file: &str, fallback: &FallbackConfig, status: StatusCode) -> Option<Response> {
+    let content = match tokio::fs::read(file).await {
+        Ok(content) => content,
+        Err(e) => {
+            log::error!("Failed to read fallback file {}: {}", file, e);
+            return None;
+        }
+    };
+    let content_type = fallback
+        .content_type
+        .clone()
+        .unwrap_or_else(|| from_path(file).first_or_octet_stream().to_string());
+    build_reply(status, &content_type, warp::hyper::Body::from(content))
+}
+
+fn template_reply(
+    template: &str,
+    fallback: &FallbackConfig,
+    status: StatusCode,
+    error: &ProxyError,
+    request_id: &str,
+) -> Option<Response> {
+    let body = template
+        .replace("{status}", status.as_str())
+        .replace("{code}", error.code())
+        .replace("{message}", &xml_escape(&error.to_string()))
+        .replace("{request_id}", &xml_escape(request_id));
+    let content_type = fallback
+        .content_type
+        .as_deref()
+        .unwrap_or("text/html; charset=utf-8");
+    build_reply(status, content_type, warp::hyper::Body::from(body))
+}
+
+fn build_reply(status: StatusCode, content_type: &str, body: warp::hyper::Body) -> Option<Response> {
+    warp::http::Response::builder()
+        .status(status)
+        .header("Content-Type", content_type)
+        .body(body)
+        .ok()
+}
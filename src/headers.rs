@@ -0,0 +1,126 @@
+use warp::http::header::{HeaderName, HeaderValue};
+use warp::http::HeaderMap;
+
+use crate::config::header_config::HeaderConfig;
+use crate::config;
+use crate::utils::wildcard_match_ignore_case;
+
+// 默认的 CSP，禁止 HTML/SVG 中的脚本和外部资源
+const DEFAULT_CONTENT_SECURITY_POLICY: &str = "default-src 'none'; img-src 'self' data:; style-src 'unsafe-inline'; sandbox";
+
+// 按 power 配置的响应头策略处理转发给客户端的响应头
+pub fn apply_header_policy(config_key: &str, object_key: &str, headers: &mut HeaderMap) {
+    let config = config::current();
+    let policy = match config
+        .power_config(config_key)
+        .and_then(|power| power.headers.as_ref()) {
+        Some(policy) => policy,
+        None => return,
+    };
+
+    strip_headers(policy, headers);
+
+    let content_type = headers
+        .get("Content-Type")
+        .and_then(|v| v.to_str().ok())
+        .map(mime_essence)
+        .unwrap_or_default();
+
+    if let Some(value) = cache_control(policy, object_key, &content_type) {
+        insert(headers, "Cache-Control", value);
+    }
+
+    if policy.security_headers {
+        insert(headers, "X-Content-Type-Options", "nosniff");
+        if is_active_content(&content_type) {
+            let csp = policy
+                .content_security_policy
+                .as_deref()
+                .unwrap_or(DEFAULT_CONTENT_SECURITY_POLICY);
+            insert(headers, "Content-Security-Policy", csp);
+        }
+    }
+
+    if let Some(types) = &policy.force_attachment {
+        if types.iter().any(|t| wildcard_match_ignore_case(t, &content_type)) {
+            force_attachment(headers);
+        }
+    }
+
+    if let Some(set) = &policy.set {
+        for (key, value) in set {
+            insert(headers, key, value);
+        }
+    }
+}
+
+fn strip_headers(policy: &HeaderConfig, headers: &mut HeaderMap) {
+    let patterns = match &policy.strip {
+        Some(patterns) => patterns,
+        None => return,
+    };
+    let names: Vec<HeaderName> = headers
+        .keys()
+        .filter(|name| patterns.iter().any(|p| wildcard_match_ignore_case(p, name.as_str())))
+        .cloned()
+        .collect();
+    for name in names {
+        headers.remove(name);
+    }
+}
+
+fn cache_control<'a>(policy: &'a HeaderConfig, object_key: &str, content_type: &str) -> Option<&'a str> {
+    policy.cache_control.as_ref()?.iter()
+        .find(|rule| {
+            let path_matched = rule
+                .pattern
+                .as_ref()
+                .is_none_or(|pattern| wildcard_match_ignore_case(pattern, object_key));
+            let type_matched = rule
+                .content_type
+                .as_ref()
+                .is_none_or(|pattern| wildcard_match_ignore_case(pattern, content_type));
+            path_matched && type_matched
+        })
+        .map(|rule| rule.value.as_str())
+}
+
+// 将 inline 改为 attachment，保留原有的文件名参数
+fn force_attachment(headers: &mut HeaderMap) {
+    let current = headers
+        .get("Content-Disposition")
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("");
+    let params = current
+        .split_once(';')
+        .map(|(_, params)| params.trim())
+        .unwrap_or("");
+    let value = if params.is_empty() {
+        "attachment".to_string()
+    } else {
+        format!("attachment; {}", params)
+    };
+    insert(headers, "Content-Disposition", &value);
+}
+
+fn is_active_content(content_type: &str) -> bool {
+    matches!(content_type, "text/html" | "application/xhtml+xml" | "image/svg+xml" | "text/xml" | "application/xml")
+}
+
+fn mime_essence(content_type: &str) -> String {
+    content_type
+        .split(';')
+        .next()
+        .unwrap_or("")
+        .trim()
+        .to_ascii_lowercase()
+}
+
+fn insert(headers: &mut HeaderMap, key: &str, value: &str) {
+    match (HeaderName::from_bytes(key.as_bytes()), HeaderValue::from_str(value)) {
+        (Ok(name), Ok(value)) => {
+            headers.insert(name, value);
+        }
+        _ => log::warn!("Invalid response header: {}: {}", key, value),
+    }
+}
@@ -0,0 +1,75 @@
+use std::collections::BTreeMap;
+
+use serde_json::json;
+use warp::http::StatusCode;
+use warp::Rejection;
+
+use crate::auth::AuthType;
+use crate::cache;
+use crate::config;
+use crate::config::power_config::BackendKind;
+use crate::minio::minio_pool::MinioPool;
+
+// 存活探针，进程能响应即可
+pub async fn healthz() -> Result<Box<dyn warp::Reply>, Rejection> {
+    Ok(Box::new(warp::reply::json(&json!({ "status": "ok" }))))
+}
+
+// 就绪探针：每个 power 至少有一个可用的 MinIO 实例或 fs 根目录存在，Bearer 鉴权时 Redis 可以连接
+pub async fn readyz() -> Result<Box<dyn warp::Reply>, Rejection> {
+    let config = config::current();
+    let mut ready = true;
+
+    // power -> (可用实例数, 实例总数)
+    let mut instances: BTreeMap<String, (usize, usize)> = BTreeMap::new();
+    for status in MinioPool::statuses().await {
+        let entry = instances.entry(status.power).or_default();
+        entry.1 += 1;
+        if status.is_healthy && !status.drained {
+            entry.0 += 1;
+        }
+    }
+    let mut minio = Vec::new();
+    let mut fs = Vec::new();
+    // 没有配置 power 时检查 default；fs 后端只检查根目录
+    let mut required = Vec::new();
+    let mut powers: Vec<_> = config.power.iter().flatten().collect();
+    powers.sort_by(|a, b| a.0.cmp(b.0));
+    for (key, power_config) in powers {
+        if power_config.backend == BackendKind::Fs {
+            let root = power_config.root.as_deref().unwrap_or_default();
+            let ok = tokio::fs::metadata(root).await.is_ok_and(|meta| meta.is_dir());
+            ready &= ok;
+            fs.push(json!({ "power": key, "root": root, "ok": ok }));
+        } else {
+            required.push(key.clone());
+        }
+    }
+    if config.power.as_ref().is_none_or(|power| power.is_empty()) && config.default.minio_config.is_some() {
+        required.push("default".to_string());
+    }
+    required.sort();
+    for power in &required {
+        let (healthy, total) = instances.get(power).copied().unwrap_or_default();
+        ready &= healthy > 0;
+        minio.push(json!({ "power": power, "healthy": healthy, "total": total, "ok": healthy > 0 }));
+    }
+
+    let mut redis = Vec::new();
+    if let Some(AuthType::Bearer(_)) = &config.auth_type {
+        for (key, result) in cache::ping_redis_pools().await {
+            ready &= result.is_ok();
+            redis.push(json!({ "key": key, "ok": result.is_ok(), "error": result.err() }));
+        }
+    }
+
+    let body = json!({
+        "status": if ready { "ready" } else { "not_ready" },
+        "config": { "ok": true, "path": config::config_path() },
+        "minio": minio,
+        "fs": fs,
+        "redis": redis,
+    });
+    let status = if ready { StatusCode::OK } else { StatusCode::SERVICE_UNAVAILABLE };
+    Ok(Box::new(warp::reply::with_status(warp::reply::json(&body), status)))
+}
@@ -0,0 +1,457 @@
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use bytes::Bytes;
+use lazy_static::lazy_static;
+use minio::s3::utils::from_http_header_value;
+use serde::Serialize;
+use warp::http::{HeaderMap, HeaderValue, StatusCode};
+
+use crate::backend::{self, ObjectResponse};
+use crate::coalesce;
+use crate::config;
+use crate::config::hot_cache_config::HotCacheConfig;
+use crate::error::ProxyError;
+use crate::rewrite;
+
+const DEFAULT_MAX_OBJECT_SIZE: u64 = 256 * 1024;
+const DEFAULT_MAX_MEMORY: u64 = 64 * 1024 * 1024;
+const DEFAULT_TTL_MS: u64 = 60 * 1000;
+// 命中的响应带上该响应头，便于排查
+const CACHE_HEADER: &str = "x-cache";
+// 有这些请求头时不使用缓存，直接读取上游
+const BYPASS_HEADERS: [&str; 3] = ["range", "if-match", "if-unmodified-since"];
+
+// (路径中的桶, 公开的对象 key)
+type EntryKey = (Option<String>, String);
+
+struct Entry {
+    headers: HeaderMap,
+    body: Bytes,
+    expires_at: Instant,
+    // 最近一次访问的序号，对应 PowerCache::recent 中的键
+    tick: u64,
+    size: u64,
+}
+
+// 内存上限按 power 分别计算，多个 power 配置 hot-cache 时总占用为各自 max-memory 之和
+#[derive(Default)]
+struct PowerCache {
+    entries: HashMap<EntryKey, Entry>,
+    // 访问序号 -> key，序号最小的最久未访问
+    recent: BTreeMap<u64, EntryKey>,
+    // 正在读取上游的 key -> (代数, 读取数)，清除时代数加一，读取开始后被清除的响应不再写入缓存
+    fetching: HashMap<EntryKey, (u64, usize)>,
+    tick: u64,
+    memory: u64,
+    hits: u64,
+    misses: u64,
+    evictions: u64,
+}
+
+// 管理接口展示的缓存统计
+#[derive(Serialize, Debug)]
+pub struct HotCacheStats {
+    power: String,
+    entries: usize,
+    memory: u64,
+    hits: u64,
+    misses: u64,
+    evictions: u64,
+}
+
+lazy_static! {
+    static ref CACHES: Mutex<HashMap<String, PowerCache>> = Mutex::new(HashMap::new());
+}
+
+// 一次上游读取，开始时记下 key 的代数，结束(包括出错返回)时减少读取数
+struct Fetch {
+    power: String,
+    key: EntryKey,
+    generation: u64,
+}
+
+impl Fetch {
+    fn begin(power: &str, key: &EntryKey) -> Fetch {
+        let mut caches = CACHES.lock().unwrap_or_else(|e| e.into_inner());
+        let fetching = caches.entry(power.to_string()).or_default().fetching.entry(key.clone()).or_default();
+        fetching.1 += 1;
+        Fetch {
+            power: power.to_string(),
+            key: key.clone(),
+            generation: fetching.0,
+        }
+    }
+
+    // 读取期间 key 被清除过时丢弃响应，避免把写入前的旧内容放回缓存
+    fn insert(&self, entry: Entry, max_memory: u64) -> bool {
+        let mut caches = CACHES.lock().unwrap_or_else(|e| e.into_inner());
+        let cache = caches.entry(self.power.clone()).or_default();
+        if cache.fetching.get(&self.key).map(|fetching| fetching.0) != Some(self.generation) {
+            return false;
+        }
+        cache.insert(self.key.clone(), entry, max_memory);
+        true
+    }
+}
+
+impl Drop for Fetch {
+    fn drop(&mut self) {
+        let mut caches = CACHES.lock().unwrap_or_else(|e| e.into_inner());
+        let Some(cache) = caches.get_mut(&self.power) else { return };
+        if let Some(fetching) = cache.fetching.get_mut(&self.key) {
+            fetching.1 -= 1;
+            if fetching.1 == 0 {
+                cache.fetching.remove(&self.key);
+            }
+        }
+    }
+}
+
+impl PowerCache {
+    fn touch(&mut self, key: &EntryKey) {
+        self.tick += 1;
+        if let Some(entry) = self.entries.get_mut(key) {
+            self.recent.remove(&entry.tick);
+            entry.tick = self.tick;
+            self.recent.insert(self.tick, key.clone());
+        }
+    }
+
+    fn remove(&mut self, key: &EntryKey) -> bool {
+        match self.entries.remove(key) {
+            Some(entry) => {
+                self.recent.remove(&entry.tick);
+                self.memory -= entry.size;
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn insert(&mut self, key: EntryKey, mut entry: Entry, max_memory: u64) {
+        self.remove(&key);
+        if entry.size > max_memory {
+            return;
+        }
+        while self.memory + entry.size > max_memory {
+            let oldest = match self.recent.first_key_value() {
+                Some((_, oldest)) => oldest.clone(),
+                None => break,
+            };
+            self.remove(&oldest);
+            self.evictions += 1;
+        }
+        self.tick += 1;
+        entry.tick = self.tick;
+        self.memory += entry.size;
+        self.recent.insert(self.tick, key.clone());
+        self.entries.insert(key, entry);
+    }
+}
+
+// 读取对象，power 配置 hot-cache 时小对象从内存返回
+pub async fn get(
+    config_key: &str,
+    route_bucket: Option<&str>,
+    object_key: &str,
+    headers: &HeaderMap,
+) -> Result<ObjectResponse, ProxyError> {
+    let hot_cache = config::current().power_config(config_key).and_then(|power| power.hot_cache.clone());
+    let hot_cache = match hot_cache {
+        Some(hot_cache) if !BYPASS_HEADERS.iter().any(|name| headers.contains_key(*name)) => hot_cache,
+        _ => return coalesce::get(config_key, route_bucket, object_key, headers).await,
+    };
+
+    let key: EntryKey = (route_bucket.map(str::to_string), object_key.to_string());
+    // 客户端要求重新验证时跳过查找，读取后刷新缓存
+    let revalidate = headers
+        .get("cache-control")
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.contains("no-cache"));
+    if !revalidate {
+        if let Some(response) = lookup(config_key, &key, headers) {
+            return Ok(response);
+        }
+    }
+
+    // 读取完整对象，条件请求在本地判断，之后的请求才能命中
+    let fetch = Fetch::begin(config_key, &key);
+    let response = coalesce::get(config_key, route_bucket, object_key, &HeaderMap::new()).await?;
+    CACHES.lock().unwrap_or_else(|e| e.into_inner()).entry(config_key.to_string()).or_default().misses += 1;
+    let ttl = match cache_ttl(&response, &hot_cache) {
+        Some(ttl) => ttl,
+        None => return Ok(conditional(response, headers)),
+    };
+    let body = warp::hyper::body::to_bytes(response.body)
+        .await
+        .map_err(|e| ProxyError::Upstream(e.to_string()))?;
+    let entry = Entry {
+        size: body.len() as u64 + response.headers.iter().map(|(k, v)| (k.as_str().len() + v.len()) as u64).sum::<u64>(),
+        headers: response.headers,
+        body,
+        expires_at: Instant::now() + ttl,
+        tick: 0,
+    };
+    let reply = respond(&entry, headers, "MISS");
+    fetch.insert(entry, hot_cache.max_memory.unwrap_or(DEFAULT_MAX_MEMORY));
+    Ok(reply)
+}
+
+fn lookup(config_key: &str, key: &EntryKey, headers: &HeaderMap) -> Option<ObjectResponse> {
+    let mut caches = CACHES.lock().unwrap_or_else(|e| e.into_inner());
+    let cache = caches.get_mut(config_key)?;
+    match cache.entries.get(key) {
+        Some(entry) if entry.expires_at > Instant::now() => {}
+        Some(_) => {
+            cache.remove(key);
+            return None;
+        }
+        None => return None,
+    }
+    cache.touch(key);
+    cache.hits += 1;
+    cache.entries.get(key).map(|entry| respond(entry, headers, "HIT"))
+}
+
+// 可以缓存时返回缓存时间：完整的 200 响应、大小已知且不超过上限、Cache-Control 允许
+fn cache_ttl(response: &ObjectResponse, hot_cache: &HotCacheConfig) -> Option<Duration> {
+    if response.status != StatusCode::OK {
+        return None;
+    }
+    let size = response
+        .headers
+        .get("content-length")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse::<u64>().ok())?;
+    if size > hot_cache.max_object_size.unwrap_or(DEFAULT_MAX_OBJECT_SIZE) {
+        return None;
+    }
+
+    let mut ttl = Duration::from_millis(hot_cache.ttl_ms.unwrap_or(DEFAULT_TTL_MS));
+    let cache_control = response.headers.get("cache-control").and_then(|v| v.to_str().ok()).unwrap_or_default();
+    for directive in cache_control.split(',').map(|d| d.trim().to_ascii_lowercase()) {
+        match directive.split_once('=') {
+            None if matches!(directive.as_str(), "no-store" | "no-cache" | "private") => return None,
+            Some(("max-age" | "s-maxage", seconds)) => {
+                let seconds = seconds.trim_matches('"').parse::<u64>().ok()?;
+                ttl = ttl.min(Duration::from_secs(seconds));
+            }
+            _ => {}
+        }
+    }
+    (!ttl.is_zero()).then_some(ttl)
+}
+
+fn respond(entry: &Entry, headers: &HeaderMap, cache: &'static str) -> ObjectResponse {
+    let mut response_headers = entry.headers.clone();
+    response_headers.insert(CACHE_HEADER, HeaderValue::from_static(cache));
+    conditional(
+        ObjectResponse {
+            status: StatusCode::OK,
+            headers: response_headers,
+            body: warp::hyper::Body::from(entry.body.clone()),
+        },
+        headers,
+    )
+}
+
+// 按请求的 If-None-Match、If-Modified-Since 把完整响应转换为 304
+fn conditional(response: ObjectResponse, headers: &HeaderMap) -> ObjectResponse {
+    if response.status != StatusCode::OK {
+        return response;
+    }
+    let etag = response.headers.get("etag").and_then(|v| v.to_str().ok()).unwrap_or_default();
+    let last_modified = response
+        .headers
+        .get("last-modified")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| from_http_header_value(v).ok());
+    if !backend::not_modified(headers, etag, last_modified) {
+        return response;
+    }
+    let mut not_modified_headers = HeaderMap::new();
+    for name in ["etag", "last-modified", "cache-control", CACHE_HEADER] {
+        if let Some(value) = response.headers.get(name) {
+            not_modified_headers.insert(name, value.clone());
+        }
+    }
+    ObjectResponse {
+        status: StatusCode::NOT_MODIFIED,
+        headers: not_modified_headers,
+        body: warp::hyper::Body::empty(),
+    }
+}
+
+// 清除缓存：power 为 None 时清除全部，key 为完整的对象 key，prefix 为 key 的前缀，都为 None 时清除整个 power
+pub fn purge(power: Option<&str>, key: Option<&str>, prefix: Option<&str>) -> usize {
+    purge_where(power, |object_key| match (key, prefix) {
+        (Some(key), _) => object_key == key,
+        (None, Some(prefix)) => object_key.starts_with(prefix),
+        (None, None) => true,
+    })
+}
+
+// WebDAV、S3 接口按存储中的 key 写入或删除对象后清除缓存，缓存按公开 key 保存，比较前按 rewrite 规则转换；
+// collection 为 true 时清除 stored_key 前缀下的全部对象
+pub fn purge_stored(power: &str, stored_key: &str, collection: bool) -> usize {
+    purge_where(Some(power), |object_key| {
+        let stored = rewrite::rewrite_object_key(power, object_key);
+        if collection {
+            stored.starts_with(stored_key)
+        } else {
+            stored == stored_key
+        }
+    })
+}
+
+fn purge_where(power: Option<&str>, matches: impl Fn(&str) -> bool) -> usize {
+    let mut caches = CACHES.lock().unwrap_or_else(|e| e.into_inner());
+    let mut purged = 0;
+    for (name, cache) in caches.iter_mut() {
+        if power.is_some_and(|power| power != name) {
+            continue;
+        }
+        let keys: Vec<EntryKey> = cache.entries.keys().filter(|(_, object_key)| matches(object_key)).cloned().collect();
+        for entry_key in keys {
+            purged += cache.remove(&entry_key) as usize;
+        }
+        for ((_, object_key), fetching) in cache.fetching.iter_mut() {
+            if matches(object_key) {
+                fetching.0 += 1;
+            }
+        }
+    }
+    purged
+}
+
+pub fn stats() -> Vec<HotCacheStats> {
+    let caches = CACHES.lock().unwrap_or_else(|e| e.into_inner());
+    let mut stats: Vec<HotCacheStats> = caches
+        .iter()
+        .map(|(power, cache)| HotCacheStats {
+            power: power.clone(),
+            entries: cache.entries.len(),
+            memory: cache.memory,
+            hits: cache.hits,
+            misses: cache.misses,
+            evictions: cache.evictions,
+        })
+        .collect();
+    stats.sort_by(|a, b| a.power.cmp(&b.power));
+    stats
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(size: usize, ttl: Duration) -> Entry {
+        Entry {
+            headers: HeaderMap::new(),
+            body: Bytes::from(vec![0; size]),
+            expires_at: Instant::now() + ttl,
+            tick: 0,
+            size: size as u64,
+        }
+    }
+
+    fn key(object_key: &str) -> EntryKey {
+        (None, object_key.to_string())
+    }
+
+    fn response(status: StatusCode, headers: &[(&'static str, &str)]) -> ObjectResponse {
+        let mut header_map = HeaderMap::new();
+        for (name, value) in headers {
+            header_map.insert(*name, HeaderValue::from_str(value).unwrap());
+        }
+        ObjectResponse {
+            status,
+            headers: header_map,
+            body: warp::hyper::Body::empty(),
+        }
+    }
+
+    #[test]
+    fn cache_control_decides_ttl() {
+        let config = HotCacheConfig {
+            max_object_size: Some(100),
+            max_memory: None,
+            ttl_ms: Some(60_000),
+        };
+        let ttl = |status, headers: &[(&'static str, &str)]| cache_ttl(&response(status, headers), &config);
+        assert_eq!(ttl(StatusCode::OK, &[("content-length", "10")]), Some(Duration::from_secs(60)));
+        assert_eq!(ttl(StatusCode::OK, &[("content-length", "10"), ("cache-control", "public, max-age=5")]), Some(Duration::from_secs(5)));
+        assert_eq!(ttl(StatusCode::OK, &[("content-length", "10"), ("cache-control", "s-maxage=600")]), Some(Duration::from_secs(60)));
+        assert_eq!(ttl(StatusCode::OK, &[("content-length", "10"), ("cache-control", "max-age=0")]), None);
+        assert_eq!(ttl(StatusCode::OK, &[("content-length", "10"), ("cache-control", "No-Store")]), None);
+        assert_eq!(ttl(StatusCode::OK, &[("content-length", "10"), ("cache-control", "private, max-age=60")]), None);
+        assert_eq!(ttl(StatusCode::OK, &[("content-length", "10"), ("cache-control", "no-cache")]), None);
+        assert_eq!(ttl(StatusCode::OK, &[("content-length", "101")]), None);
+        assert_eq!(ttl(StatusCode::OK, &[]), None);
+        assert_eq!(ttl(StatusCode::PARTIAL_CONTENT, &[("content-length", "10")]), None);
+    }
+
+    #[test]
+    fn least_recently_used_is_evicted_at_memory_cap() {
+        let mut cache = PowerCache::default();
+        let ttl = Duration::from_secs(60);
+        cache.insert(key("a"), entry(40, ttl), 100);
+        cache.insert(key("b"), entry(40, ttl), 100);
+        cache.touch(&key("a"));
+        cache.insert(key("c"), entry(40, ttl), 100);
+        assert!(cache.entries.contains_key(&key("a")));
+        assert!(!cache.entries.contains_key(&key("b")));
+        assert!(cache.entries.contains_key(&key("c")));
+        assert_eq!((cache.memory, cache.evictions), (80, 1));
+
+        // 替换同一个 key 不算淘汰，超过上限的对象不写入
+        cache.insert(key("a"), entry(50, ttl), 100);
+        assert_eq!((cache.memory, cache.evictions), (90, 1));
+        cache.insert(key("d"), entry(101, ttl), 100);
+        assert!(!cache.entries.contains_key(&key("d")));
+        assert_eq!((cache.entries.len(), cache.memory), (2, 90));
+        assert_eq!(cache.recent.len(), cache.entries.len());
+    }
+
+    #[test]
+    fn expired_entries_are_removed_on_lookup() {
+        let power = "test-expiry";
+        {
+            let mut caches = CACHES.lock().unwrap();
+            let cache = caches.entry(power.to_string()).or_default();
+            cache.insert(key("old"), entry(10, Duration::ZERO), 100);
+            cache.insert(key("fresh"), entry(10, Duration::from_secs(60)), 100);
+        }
+        assert!(lookup(power, &key("old"), &HeaderMap::new()).is_none());
+        let hit = lookup(power, &key("fresh"), &HeaderMap::new()).expect("fresh entry should be served");
+        assert_eq!(hit.headers.get(CACHE_HEADER).unwrap(), "HIT");
+
+        let caches = CACHES.lock().unwrap();
+        let cache = &caches[power];
+        assert!(!cache.entries.contains_key(&key("old")));
+        assert_eq!((cache.memory, cache.hits), (10, 1));
+    }
+
+    #[test]
+    fn purge_during_fetch_skips_stale_insert() {
+        let power = "test-purge";
+        let stale = Fetch::begin(power, &key("img/a.png"));
+        let other = Fetch::begin(power, &key("css/a.css"));
+        purge(Some(power), None, Some("img/"));
+        assert!(!stale.insert(entry(10, Duration::from_secs(60)), 100));
+        assert!(other.insert(entry(10, Duration::from_secs(60)), 100));
+
+        // 清除之后开始的读取可以写入
+        let fresh = Fetch::begin(power, &key("img/a.png"));
+        assert!(fresh.insert(entry(10, Duration::from_secs(60)), 100));
+        drop((stale, other, fresh));
+
+        let caches = CACHES.lock().unwrap();
+        let cache = &caches[power];
+        assert!(cache.entries.contains_key(&key("img/a.png")));
+        assert!(cache.entries.contains_key(&key("css/a.css")));
+        assert!(cache.fetching.is_empty());
+    }
+}
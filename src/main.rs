@@ -1,47 +1,116 @@
 use std::collections::HashMap;
-use std::env;
 use std::string::String;
+use std::time::Duration;
 
+use clap::Parser;
 use lazy_static::lazy_static;
 use mime_guess::from_path;
+use tracing::Instrument;
 use warp::{Filter, Rejection};
-use warp::http::HeaderMap;
+use warp::http::{HeaderMap, HeaderValue};
 
-use crate::auth::{error_reply, ErrorReply};
-use crate::minio::minio_pool::{MINIO_POOLS, MinioPool};
+use crate::error::{error_reply, ProxyError};
+use crate::minio::minio_pool::MinioPool;
 
+mod admin;
 mod config;
+mod dav;
+mod disposition;
 mod auth;
+mod backend;
 mod cache;
+mod cli;
+mod coalesce;
+mod error;
+mod fallback;
+mod headers;
+mod health;
+mod hot_cache;
 mod minio;
+mod mirror;
+mod replication;
+mod rewrite;
+mod routing;
+mod s3;
+mod trace;
+mod upload;
+mod utils;
+mod writes;
+
+// 转发预签名请求时建立连接的超时，fetch.header-timeout-ms 更短时以它为准
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
 
 // 全局静态变量连接池
 lazy_static! {
-    static ref CLIENT: reqwest::Client = reqwest::Client::builder()
+    // 首次使用时按当前配置加载 ca-cert，修改证书后需要重启
+    static ref CLIENT: reqwest::Client = minio::r2d2_minio::trusted_certificates(config::current().minio_configs())
+        .into_iter()
+        .fold(reqwest::Client::builder(), |builder, certificate| builder.add_root_certificate(certificate))
+        .connect_timeout(CONNECT_TIMEOUT)
         .build()
         .unwrap();
 }
 
 #[tokio::main]
 async fn main() {
-    env::set_var("RUST_LOG", "info");
-    env_logger::init();
+    let cli = cli::Cli::parse();
+    if let Some(path) = cli.config {
+        config::set_config_path(path);
+    }
+
+    let code = match cli.command.unwrap_or(cli::Command::Serve) {
+        cli::Command::Serve => {
+            serve().await;
+            0
+        }
+        cli::Command::Check => {
+            trace::init_logging();
+            cli::check().await
+        }
+        cli::Command::Dump => cli::dump(),
+        cli::Command::TestObject { path } => {
+            trace::init_logging();
+            cli::test_object(&path).await
+        }
+    };
+    std::process::exit(code);
+}
+
+async fn serve() {
+    trace::init_tracing();
     cache::initialize_redis_pools();
-    minio::minio_pool::initialize_minio_pools().await;
+    if let Err(e) = minio::minio_pool::initialize_minio_pools().await {
+        log::error!("{}", e);
+        std::process::exit(1);
+    }
 
     let cors = warp::cors()
         .allow_any_origin();
 
-    let route = warp::path::full()
-        .and(warp::query::<HashMap<String, String>>())
-        .and(warp::method())
-        .and(warp::header::headers_cloned())
-        .and_then(process)
+    // 探针接口不经过 match-prefix 路由，按域名路由到 power 的请求仍按对象处理
+    let probes = warp::get()
+        .and(routing::unrouted_host())
+        .and(warp::path!("healthz").and_then(health::healthz)
+            .or(warp::path!("readyz").and_then(health::readyz))
+            .unify());
+
+    let route = probes
+        .or(dav::routes())
+        .unify()
+        .or(warp::path::full()
+            .and(warp::query::<HashMap<String, String>>())
+            .and(warp::method())
+            .and(warp::header::headers_cloned())
+            .and(warp::body::stream().map(utils::boxed_body))
+            .and(trace::request_id())
+            .and_then(process))
+        .unify()
         .with(cors);
 
     let mut server_port = config::PORT; // 用您的默认前缀替换此处
 
-    if let Some(ref prefix) = config::WARP_MINIO_CONFIG.server_port {
+    let warp_config = config::current();
+    if let Some(ref prefix) = warp_config.server_port {
         if *prefix != 0 {
             // 假设0是一个无效的端口号
             server_port = *prefix;
@@ -50,7 +119,7 @@ async fn main() {
 
     log::info!(
     "Auth type: {}",
-    config::WARP_MINIO_CONFIG.auth_type
+    warp_config.auth_type
         .as_ref()
         .map_or("None".to_string(), |auth_type| auth_type.to_string())
     );
@@ -60,93 +129,119 @@ async fn main() {
         MinioPool::perform_health_checks().await;
     });
 
+    // 启动 direct 模式的上游连接保活任务
+    tokio::spawn(MinioPool::perform_keep_alive());
 
-    warp::serve(route).run(([127, 0, 0, 1], server_port)).await;
+    // 启动管理接口
+    tokio::spawn(admin::serve());
+
+    // 启动 S3 兼容接口
+    tokio::spawn(s3::serve());
+
+    // 启动写入复制任务
+    tokio::spawn(replication::run());
+
+
+    let (_, server) = warp::serve(route).bind_with_graceful_shutdown(([127, 0, 0, 1], server_port), trace::shutdown_signal());
+    server.await;
+    trace::shutdown_tracing().await;
 }
 
 async fn process(
     path: warp::path::FullPath,
     params: HashMap<String, String>,
-    _method: warp::http::Method,
+    method: warp::http::Method,
     headers: HeaderMap,
+    body: utils::BodyStream,
+    request_id: String,
 ) -> Result<Box<dyn warp::Reply>, Rejection> {
-    let request_uri = path.as_str();
-
-    let url_prefix = config::WARP_MINIO_CONFIG
-        .match_prefix
-        .as_deref()
-        .unwrap_or(config::URL_PREFIX);
+    let span = tracing::info_span!(
+        "request",
+        request_id = %request_id,
+        method = %method,
+        path = %path.as_str(),
+    );
+    let reply = handle(path, params, &method, headers, body, &request_id).instrument(span).await?;
+    Ok(Box::new(warp::reply::with_header(reply, config::REQUEST_ID_HEADER, request_id)))
+}
 
-    if !request_uri.starts_with(url_prefix) {
-        return Ok(Box::new(warp::reply::with_status(
-            "URI does not start with the expected prefix",
-            warp::http::StatusCode::BAD_REQUEST,
-        )));
-    }
+async fn handle(
+    path: warp::path::FullPath,
+    params: HashMap<String, String>,
+    method: &warp::http::Method,
+    headers: HeaderMap,
+    body: utils::BodyStream,
+    request_id: &str,
+) -> Result<Box<dyn warp::Reply>, Rejection> {
+    let request_uri = path.as_str();
 
-    let bucket_path = &request_uri[url_prefix.len()..];
-    let path = bucket_path.trim_start_matches('/');
-    let mut parts = path.splitn(2, '/');
-    let config_key = parts.next().unwrap_or("");
-    let object_key = parts.next().unwrap_or("");
-    let filename = params.get("filename");
+    let host = headers.get("host").and_then(|v| v.to_str().ok());
+    let route = match routing::resolve(&config::current(), host, request_uri) {
+        Ok(route) => route,
+        Err(e) => return error_reply(e, request_id),
+    };
 
     log::info!("Access: {}", request_uri);
 
-    if !auth::check(headers.clone(), config_key) {
-        return error_reply(ErrorReply::Unauthorized);
+    // PUT 上传对象，其他请求方法都按读取处理
+    if method == warp::http::Method::PUT {
+        return match upload::put_object(&route, &headers, body).await {
+            Ok(reply) => Ok(reply),
+            Err(e) => error_reply(e, request_id),
+        };
     }
 
-    let link = match minio::minio_parser::get_generate_link_by_config_key_and_object_key(config_key, object_key).await {
-        Ok(token) => token,
-        Err(e) => {
-            log::error!("Failed to generate link: {}", e);
-            return error_reply(ErrorReply::MinioInvalid);
-        }
-    };
-
-    let client_request = CLIENT.get(&link);
-    let client_request = if let Some(range_header) = headers.get("Range") {
-        client_request.header("Range", range_header)
-    } else {
-        client_request
-    };
-
-    // 发送请求并获取异步的响应流
-    let response = client_request.send().await.map_err(|_| warp::reject::reject())?;
-    let status = response.status();
-    // let headers = response.headers().clone();
-    let headers = &response.headers().clone();
-    // 使用 `hyper::Body::wrap_stream` 将响应流转换为 warp 可以发送的 Body
-    let stream = response.bytes_stream();
-    let body = warp::hyper::Body::wrap_stream(stream);
-
-    let mut response_builder = warp::http::Response::builder().status(status);
+    match fetch_object(&route, &params, &headers).await {
+        Ok(reply) => Ok(reply),
+        Err(e) => match fallback::fallback_reply(&route, &e, request_id).await {
+            Some(reply) => Ok(reply),
+            None => error_reply(e, request_id),
+        },
+    }
+}
 
-    for (key, value) in headers {
-        response_builder = response_builder.header(key, value);
+async fn fetch_object(
+    route: &routing::Route,
+    params: &HashMap<String, String>,
+    headers: &HeaderMap,
+) -> Result<Box<dyn warp::Reply>, ProxyError> {
+    let config_key = route.config_key.as_str();
+    let object_key = route.object_key.as_str();
+    if !auth::check(headers.clone(), config_key).await {
+        return Err(ProxyError::Unauthorized);
     }
 
+    // 按 power 的改写规则得到存储中的 key，由存储后端读取，不存在时按 mirror 配置回源
+    let object = hot_cache::get(config_key, route.bucket.as_deref(), object_key, headers).await?;
+    let status = object.status;
+    let mut response_headers = object.headers;
+    let body = object.body;
+
     // 如果设置了重新解析 Content-Type
-    if config::WARP_MINIO_CONFIG.parsing_content_type {
-        let content_type = re_parse_content_type(&headers, object_key);
-        response_builder = response_builder.header("Content-Type", content_type);
+    if config::current().parsing_content_type {
+        let content_type = re_parse_content_type(&response_headers, object_key);
+        if let Ok(value) = HeaderValue::from_str(&content_type) {
+            response_headers.insert("Content-Type", value);
+        }
     }
 
-    if let Some(filename) = filename {
-        let content_disposition = format!("attachment; filename=\"{}\"", filename);
-        response_builder = response_builder.header("Content-Disposition", content_disposition);
+    if let Some(content_disposition) = disposition::content_disposition(params, &response_headers, object_key) {
+        if let Ok(value) = HeaderValue::from_str(&content_disposition) {
+            response_headers.insert("Content-Disposition", value);
+        }
     }
 
-    let response = response_builder
-        .body(body)
-        .map_err(|_| warp::reject::reject())?;
+    headers::apply_header_policy(config_key, object_key, &mut response_headers);
+
+    let mut response = warp::http::Response::new(body);
+    *response.status_mut() = status;
+    *response.headers_mut() = response_headers;
 
     Ok(Box::new(response) as Box<dyn warp::Reply>)
 }
 
 
-fn re_parse_content_type(headers: &&HeaderMap, key: &str) -> String {
+fn re_parse_content_type(headers: &HeaderMap, key: &str) -> String {
     headers.get("Content-Type")
         .and_then(|v| v.to_str().ok())
         .map(|s| s.to_string())
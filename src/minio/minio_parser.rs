@@ -1,11 +1,14 @@
 use std::collections::HashMap;
+use std::time::Instant;
 
 use lazy_static::lazy_static;
+use minio::s3::args::DEFAULT_EXPIRY_SECONDS;
 use reqwest::Method;
 use tokio::sync::RwLock;
-use crate::config::WARP_MINIO_CONFIG;
-
-use crate::minio::minio_pool::MinioPool;
+use crate::config;
+use crate::error::ProxyError;
+use crate::minio::minio_pool::{self, MinioPool};
+use crate::minio::presign_cache;
 
 lazy_static!(
     static ref MINIO_KET_TO_BUCKET_MAP: RwLock<HashMap<String, String>> = {
@@ -14,47 +17,85 @@ lazy_static!(
 );
 
 
-pub async fn get_generate_link_by_config_key_and_object_key(
+// 使用存储中的对象 key 生成预签名地址，bucket 为 None 时使用 power 配置的桶
+pub async fn get_generate_link_by_stored_key(
     minio_config_key: &str,
+    bucket: Option<&str>,
     object_key: &str,
-) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
-
-    let bucket_name = get_minio_bucket_by_minio_config_key(minio_config_key).await.unwrap_or_else(|| String::from(""));
+) -> Result<String, ProxyError> {
 
+    let bucket_name = bucket_for(minio_config_key, bucket).await;
     let link = generate_minio_share_link(minio_config_key, &bucket_name, object_key).await?;
-    return Ok(link)
+    Ok(link)
+
+}
 
+// 路径中指定的桶，为 None 时使用 power 配置的桶
+pub async fn bucket_for(minio_config_key: &str, bucket: Option<&str>) -> String {
+    match bucket {
+        Some(bucket) => bucket.to_string(),
+        None => get_minio_bucket_by_minio_config_key(minio_config_key).await.unwrap_or_else(|| String::from("")),
+    }
 }
 
+#[tracing::instrument(name = "presign")]
 async fn generate_minio_share_link(
     config_key: &str,
     bucket_name: &str,
     object: &str,
-) -> Result<String, minio::s3::error::Error> {
-    let pool = MinioPool::get_minio_client(config_key).await
-        .ok_or_else(|| {
-            std::io::Error::new(std::io::ErrorKind::NotFound, "No available MinIO client found")
-        })?;
-    let client = pool.get().expect("Failed to get connection from pool");
-    let args = minio::s3::args::GetPresignedObjectUrlArgs::new(bucket_name, object, Method::GET)?;
-    client.get_presigned_object_url(&args).await.map(|r| r.url)
+) -> Result<String, ProxyError> {
+    let started = Instant::now();
+    let presign = config::current()
+        .power_config(config_key)
+        .and_then(|power| power.presign.clone())
+        .unwrap_or_default();
+    let (endpoint, pool) = MinioPool::get_minio_instance(config_key).await?;
+    let key = (config_key.to_string(), endpoint, bucket_name.to_string(), object.to_string(), Method::GET);
+    let cache = presign.cache.unwrap_or(true);
+    if cache {
+        if let Some(url) = presign_cache::get(&key) {
+            presign_cache::record(config_key, true, started.elapsed());
+            return Ok(url);
+        }
+    }
+
+    let client = minio_pool::checkout(&pool).await?;
+    let expiry_secs = presign.expiry_secs.unwrap_or(DEFAULT_EXPIRY_SECONDS);
+    let mut args = minio::s3::args::GetPresignedObjectUrlArgs::new(bucket_name, object, Method::GET)?;
+    args.expiry_seconds = Some(expiry_secs);
+    let response = client.get_presigned_object_url(&args).await?;
+    if cache {
+        presign_cache::insert(key, response.url.clone(), expiry_secs);
+    }
+    presign_cache::record(config_key, false, started.elapsed());
+    Ok(response.url)
 }
 
 
 
-async fn get_minio_bucket_by_minio_config_key(config_key: &str) -> Option<String> {
+// 清空 config_key 到桶名的缓存，返回清除的条目数
+pub async fn flush_bucket_cache() -> usize {
+    let mut map = MINIO_KET_TO_BUCKET_MAP.write().await;
+    let count = map.len();
+    map.clear();
+    count
+}
+
+#[tracing::instrument(name = "bucket_resolve")]
+pub async fn get_minio_bucket_by_minio_config_key(config_key: &str) -> Option<String> {
     let read_map = MINIO_KET_TO_BUCKET_MAP.read();
     if let Some(name) = read_map.await.get(config_key) {
         return Some(name.clone());
     }
 
-    if let Some(bucket_name) = WARP_MINIO_CONFIG.bucket_name(config_key.to_string()) {
+    let config = config::current();
+    if let Some(bucket_name) = config.bucket_name(config_key.to_string()) {
         let mut write_map = MINIO_KET_TO_BUCKET_MAP.write().await;
         write_map.insert(config_key.to_string(), bucket_name.clone());
         return Some(bucket_name);
     } else {
 
-        match &WARP_MINIO_CONFIG.default.bucket_name {
+        match &config.default.bucket_name {
             None => { log::info!("Default bucket name is None.")}
             Some(bucket_name) => {
                 return Some(bucket_name.clone())
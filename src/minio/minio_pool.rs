@@ -1,117 +1,398 @@
 use std::collections::HashMap;
-
 use std::sync::Arc;
-use std::time::Duration;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
 
+use futures_util::future::join_all;
 use lazy_static::lazy_static;
-use log::{log, warn};
-use minio::s3::args::ListBucketsArgs;
-use r2d2::Pool;
+use minio::s3::args::{BucketExistsArgs, ListBucketsArgs};
+use minio::s3::client::Client;
+use r2d2::{ManageConnection, Pool, PooledConnection};
+use serde::Serialize;
 use tokio::sync::RwLock;
 use tokio::time::interval;
 
+use crate::config::fetch_config::{FetchConfig, FetchMode};
 use crate::config::minio_config::MinioConfig;
-use crate::config::WARP_MINIO_CONFIG;
+use crate::config::warp_config::WarpConfig;
+use crate::config;
+use crate::error::ProxyError;
+use crate::minio::minio_parser;
 use crate::minio::r2d2_minio::MinioConnectionManager;
 
+// 未配置 fetch.header-timeout-ms 时，从连接池取客户端和健康检查的超时
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(10);
+
 lazy_static!(
      pub static ref MINIO_POOLS: Arc<RwLock<HashMap<String, Vec<MinioPoolInstance>>>> = {
         Arc::new(RwLock::new(HashMap::new()))
      };
+     // 用于轮询的当前索引
+     static ref CURRENT_INDEX: AtomicUsize = AtomicUsize::new(0);
 );
 
 
 pub struct MinioPoolInstance {
+    pub(crate) endpoint: String,
     pub(crate) pool: Pool<MinioConnectionManager>,
     pub(crate) is_healthy: bool,
+    // 被管理接口摘除的实例不再接收请求，也不参与健康检查
+    pub(crate) drained: bool,
 }
 
-pub struct MinioPool {
-    instances: RwLock<HashMap<String, MinioPoolInstance>>,
-    // 用于轮询的当前索引
-    current_index: RwLock<usize>,
+// 管理接口展示的实例状态
+#[derive(Serialize, Debug)]
+pub struct MinioInstanceStatus {
+    pub(crate) power: String,
+    pub(crate) endpoint: String,
+    pub(crate) is_healthy: bool,
+    pub(crate) drained: bool,
+    pub(crate) connections: u32,
+    pub(crate) idle_connections: u32,
 }
 
+pub struct MinioPool;
+
 impl MinioPool {
     pub async fn perform_health_checks() {
         let mut interval = interval(Duration::from_secs(60));
         loop {
             interval.tick().await;
-            let mut pools = MINIO_POOLS.write().unwrap(); // 获取写锁
-
-            for pool_instances in pools.values_mut() {
-                for instance in pool_instances {
-                    let client = match instance.pool.get() {
-                        Ok(client) => client,
-                        Err(_) => {
-                            instance.is_healthy = false;
-                            continue;
-                        }
-                    };
-
-                    instance.is_healthy = client.list_buckets(&ListBucketsArgs::default()).await.is_ok();
+            Self::check_health().await;
+        }
+    }
+
+    // 检查全部实例，检查期间不持有写锁，避免阻塞请求
+    pub async fn check_health() {
+        let targets: Vec<(String, String, Pool<MinioConnectionManager>)> = MINIO_POOLS
+            .read()
+            .await
+            .iter()
+            .flat_map(|(key, instances)| {
+                instances
+                    .iter()
+                    .filter(|instance| !instance.drained)
+                    .map(|instance| (key.clone(), instance.endpoint.clone(), instance.pool.clone()))
+            })
+            .collect();
+
+        let config = config::current();
+        let mut results = Vec::with_capacity(targets.len());
+        for (key, endpoint, pool) in targets {
+            let timeout = request_timeout(config.power_config(&key).and_then(|power| power.fetch.as_ref()));
+            let healthy = match checkout(&pool).await {
+                Ok(client) => {
+                    let args = ListBucketsArgs::default();
+                    matches!(tokio::time::timeout(timeout, client.list_buckets(&args)).await, Ok(Ok(_)))
+                }
+                Err(_) => false,
+            };
+            if !healthy {
+                log::warn!("MinIO instance {} of {} is unhealthy", endpoint, key);
+            }
+            results.push((key, endpoint, healthy));
+        }
+
+        let mut pools = MINIO_POOLS.write().await; // 获取写锁
+        for (key, endpoint, healthy) in results {
+            if let Some(instances) = pools.get_mut(&key) {
+                for instance in instances.iter_mut().filter(|instance| instance.endpoint == endpoint) {
+                    instance.is_healthy = healthy;
                 }
             }
         }
     }
 
-    pub async fn get_minio_client(config_key: &str) -> Option<Pool<MinioConnectionManager>> {
+    // 按各 power 的 fetch.keep-alive-ms 定期访问上游，空闲的客户端连接不会因超时被关闭
+    pub async fn perform_keep_alive() {
+        let mut last_run: HashMap<String, Instant> = HashMap::new();
+        let mut interval = interval(Duration::from_secs(1));
+        loop {
+            interval.tick().await;
+            let config = config::current();
+            let due: Vec<String> = config
+                .power
+                .iter()
+                .flatten()
+                .filter_map(|(key, power)| {
+                    let fetch = power.fetch.as_ref().filter(|fetch| fetch.mode == FetchMode::Direct)?;
+                    let period = Duration::from_millis(fetch.keep_alive_ms?);
+                    last_run.get(key).is_none_or(|last| last.elapsed() >= period).then(|| key.clone())
+                })
+                .collect();
+            for key in due {
+                last_run.insert(key.clone(), Instant::now());
+                Self::keep_alive(&key).await;
+            }
+        }
+    }
+
+    // 实例的每个空闲客户端对 power 的桶各发送一次 HeadBucket，只需要该桶的读取权限；
+    // 克隆的客户端共用原客户端的连接，取出后立即放回，不占用连接池
+    async fn keep_alive(config_key: &str) {
+        let bucket = minio_parser::bucket_for(config_key, None).await;
+        let args = match BucketExistsArgs::new(&bucket) {
+            Ok(args) => args,
+            Err(e) => {
+                log::debug!("Keep-alive of {} is skipped, no usable bucket: {}", config_key, e);
+                return;
+            }
+        };
+        let pools: Vec<(String, Pool<MinioConnectionManager>)> = match MINIO_POOLS.read().await.get(config_key) {
+            Some(instances) => instances
+                .iter()
+                .filter(|instance| instance.is_healthy && !instance.drained)
+                .map(|instance| (instance.endpoint.clone(), instance.pool.clone()))
+                .collect(),
+            None => return,
+        };
+        for (endpoint, pool) in pools {
+            let clients: Vec<_> = (0..pool.state().idle_connections)
+                .map_while(|_| pool.try_get())
+                .collect::<Vec<_>>()
+                .iter()
+                .map(|client| Client::clone(client))
+                .collect();
+            let results = join_all(clients.iter().map(|client| client.bucket_exists(&args))).await;
+            let failed = results.iter().filter(|result| result.is_err()).count();
+            if failed > 0 {
+                log::warn!("Keep-alive to MinIO instance {} of {} failed on {} connections", endpoint, config_key, failed);
+            }
+        }
+    }
+
+    pub async fn get_minio_client(config_key: &str) -> Result<Pool<MinioConnectionManager>, ProxyError> {
+        Self::get_minio_instance(config_key).await.map(|(_, pool)| pool)
+    }
+
+    // 轮询选择一个健康的实例，同时返回实例的 endpoint
+    pub async fn get_minio_instance(config_key: &str) -> Result<(String, Pool<MinioConnectionManager>), ProxyError> {
         let pools = MINIO_POOLS.read().await;
-        if let Some(pool_instances) = pools.get(config_key) {
-            // 实现轮询逻辑，选择一个健康的实例
-            for instance in pool_instances.iter().cycle() {
-                if instance.is_healthy {
-                    return Some(instance.pool.clone());
-                }
+        let pool_instances = pools
+            .get(config_key)
+            .ok_or_else(|| ProxyError::UnknownConfigKey(config_key.to_string()))?;
+
+        // 实现轮询逻辑，从当前索引开始选择一个健康的实例
+        let len = pool_instances.len();
+        let start = CURRENT_INDEX.fetch_add(1, Ordering::Relaxed);
+        (0..len)
+            .map(|offset| &pool_instances[(start + offset) % len])
+            .find(|instance| instance.is_healthy && !instance.drained)
+            .map(|instance| (instance.endpoint.clone(), instance.pool.clone()))
+            .ok_or_else(|| ProxyError::PoolUnavailable(format!("No healthy MinIO instance for: {}", config_key)))
+    }
+
+    pub async fn statuses() -> Vec<MinioInstanceStatus> {
+        let pools = MINIO_POOLS.read().await;
+        let mut statuses: Vec<MinioInstanceStatus> = pools
+            .iter()
+            .flat_map(|(key, instances)| {
+                instances.iter().map(|instance| {
+                    let state = instance.pool.state();
+                    MinioInstanceStatus {
+                        power: key.clone(),
+                        endpoint: instance.endpoint.clone(),
+                        is_healthy: instance.is_healthy,
+                        drained: instance.drained,
+                        connections: state.connections,
+                        idle_connections: state.idle_connections,
+                    }
+                })
+            })
+            .collect();
+        statuses.sort_by(|a, b| (&a.power, &a.endpoint).cmp(&(&b.power, &b.endpoint)));
+        statuses
+    }
+
+    // 摘除或恢复实例，返回匹配到的实例数量
+    pub async fn set_drained(config_key: &str, endpoint: &str, drained: bool) -> usize {
+        let mut pools = MINIO_POOLS.write().await;
+        let mut count = 0;
+        if let Some(instances) = pools.get_mut(config_key) {
+            for instance in instances.iter_mut().filter(|instance| instance.endpoint == endpoint) {
+                instance.drained = drained;
+                count += 1;
             }
         }
-        None
+        if count > 0 {
+            log::info!("MinIO instance {} of {} drained: {}", endpoint, config_key, drained);
+        }
+        count
     }
 }
 
 
-pub async fn initialize_minio_pools() {
-    match &WARP_MINIO_CONFIG.default.minio_config {
+// 从连接池取出一个客户端；r2d2 的 get 会阻塞线程直到有空闲连接或超时，放到阻塞线程池中执行
+pub async fn checkout(pool: &Pool<MinioConnectionManager>) -> Result<PooledConnection<MinioConnectionManager>, ProxyError> {
+    let pool = pool.clone();
+    tokio::task::spawn_blocking(move || pool.get())
+        .await
+        .map_err(|e| ProxyError::PoolUnavailable(e.to_string()))?
+        .map_err(|e| ProxyError::PoolUnavailable(e.to_string()))
+}
+
+// 等待上游的超时，取 power 的 fetch.header-timeout-ms
+fn request_timeout(fetch: Option<&FetchConfig>) -> Duration {
+    fetch
+        .and_then(|fetch| fetch.header_timeout_ms)
+        .map_or(DEFAULT_TIMEOUT, Duration::from_millis)
+}
+
+// 各 power 的 MinIO 实例
+pub type MinioPools = HashMap<String, Vec<MinioPoolInstance>>;
+
+// 按当前配置创建并替换全部 MinIO 连接池
+pub async fn initialize_minio_pools() -> Result<(), ProxyError> {
+    let pools = create_pools(&config::current())?;
+    install_pools(pools).await;
+    Ok(())
+}
+
+// 按配置创建全部 MinIO 连接池，任一实例创建失败时返回错误，重新加载配置时在替换前调用
+pub fn create_pools(config: &WarpConfig) -> Result<MinioPools, ProxyError> {
+    let mut pools = HashMap::new();
+
+    match &config.default.minio_config {
         None => log::info!("Minio default config is None"),
         Some(configs) => {
-            insert_pool( String::from("default"), &configs).await;
+            pools.insert(String::from("default"), create_pool("default", configs, None)?);
         }
     }
 
-    match &WARP_MINIO_CONFIG.power {
+    match &config.power {
         None => log::info!("Power config is None"),
         Some(power) => {
-            for power_key in power.keys() {
-                let power_value = power.get(power_key);
-                if let Some(minio_configs) = &power_value.unwrap().minio_config {
-                    insert_pool(power_key.to_string(), minio_configs).await;
+            for (power_key, power_value) in power {
+                if let Some(minio_configs) = &power_value.minio_config {
+                    let fetch = power_value.fetch.as_ref();
+                    pools.insert(power_key.to_string(), create_pool(power_key, minio_configs, fetch)?);
                 }
             }
         }
     }
+    Ok(pools)
+}
 
+// 替换全部连接池，仍然存在的实例保留被摘除的状态
+pub async fn install_pools(mut pools: MinioPools) {
+    let mut current = MINIO_POOLS.write().await;
+    carry_over_drained(&current, &mut pools);
+    *current = pools;
     log::info!("MinIO pools initialization completed");
 }
 
+fn carry_over_drained(previous: &MinioPools, pools: &mut MinioPools) {
+    for (key, instances) in pools.iter_mut() {
+        let Some(previous) = previous.get(key) else { continue };
+        for instance in instances.iter_mut() {
+            instance.drained = previous
+                .iter()
+                .any(|old| old.drained && old.endpoint == instance.endpoint);
+        }
+    }
+}
 
-async fn insert_pool(config_key: String, configs: &Vec<MinioConfig>) {
-    let mut pools = MINIO_POOLS.write();
-
+fn create_pool(config_key: &str, configs: &Vec<MinioConfig>, fetch: Option<&FetchConfig>) -> Result<Vec<MinioPoolInstance>, ProxyError> {
     let mut pool_instances = Vec::new();
     for config in configs {
-        let manager = MinioConnectionManager::new(
-            config.endpoint.clone(),
-            config.access_key.clone(),
-            config.secret_key.clone(),
-        );
+        let manager = MinioConnectionManager::new(config.clone());
+        // 客户端创建失败时 r2d2 会一直重试到超时，先创建一次以便直接报出错误
+        let error = |e: String| {
+            ProxyError::Config(format!("Failed to create MinIO pool {} of {}: {}", config.endpoint, config_key, e))
+        };
+        manager.connect().map_err(|e| error(e.to_string()))?;
         let pool = Pool::builder()
             .min_idle(config.idle_pool_size)
             .max_size(config.max_pool_size.unwrap_or(8))
+            // 连接池占满或上游不可用时，取客户端最多等待该时间
+            .connection_timeout(request_timeout(fetch))
             .build(manager)
-            .expect("Failed to create MinIO pool");
+            .map_err(|e| error(e.to_string()))?;
 
-        pool_instances.push(MinioPoolInstance { pool, is_healthy: true });
+        pool_instances.push(MinioPoolInstance {
+            endpoint: config.endpoint.clone(),
+            pool,
+            is_healthy: true,
+            drained: false,
+        });
     }
-    pools.await.insert(config_key, pool_instances);
+    Ok(pool_instances)
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn warp_config(yaml: &str) -> WarpConfig {
+        serde_yaml::from_str(yaml).unwrap()
+    }
+
+    fn minio(endpoint: &str) -> String {
+        format!("{{endpoint: '{}', access-key: a, secret-key: b}}", endpoint)
+    }
+
+    #[test]
+    fn invalid_instance_fails_the_whole_build() {
+        let config = warp_config(&format!(
+            "power: {{ok: {{minio-config: [{}]}}, bad: {{minio-config: [{}, {}]}}}}",
+            minio("http://127.0.0.1:9000"),
+            minio("http://127.0.0.1:9001"),
+            minio("ftp://127.0.0.1:9002"),
+        ));
+        let error = create_pools(&config).err().expect("build should fail");
+        let message = error.to_string();
+        assert!(message.contains("ftp://127.0.0.1:9002 of bad"), "{}", message);
+    }
+
+    #[test]
+    fn drained_state_survives_rebuild() {
+        let yaml = format!(
+            "power: {{a: {{minio-config: [{}, {}]}}, b: {{minio-config: [{}]}}}}",
+            minio("http://127.0.0.1:9000"),
+            minio("http://127.0.0.1:9001"),
+            minio("http://127.0.0.1:9000"),
+        );
+        let mut previous = create_pools(&warp_config(&yaml)).unwrap();
+        previous.get_mut("a").unwrap()[0].drained = true;
+
+        // 重新加载时去掉了 9001，新增 9002
+        let yaml = format!(
+            "power: {{a: {{minio-config: [{}, {}]}}, b: {{minio-config: [{}]}}}}",
+            minio("http://127.0.0.1:9002"),
+            minio("http://127.0.0.1:9000"),
+            minio("http://127.0.0.1:9000"),
+        );
+        let mut pools = create_pools(&warp_config(&yaml)).unwrap();
+        carry_over_drained(&previous, &mut pools);
+        let drained = |key: &str| pools[key].iter().map(|instance| (instance.endpoint.as_str(), instance.drained)).collect::<Vec<_>>();
+        assert_eq!(drained("a"), [("http://127.0.0.1:9002", false), ("http://127.0.0.1:9000", true)]);
+        // 摘除状态只对同一 power 生效
+        assert_eq!(drained("b"), [("http://127.0.0.1:9000", false)]);
+    }
+
+    // 连接池占满时按 header-timeout-ms 返回错误，等待期间不阻塞运行时的其他任务
+    #[tokio::test]
+    async fn checkout_waits_off_the_runtime() {
+        let config = warp_config(
+            "power: {a: {fetch: {header-timeout-ms: 300}, minio-config: [{endpoint: 'http://127.0.0.1:9000', access-key: a, secret-key: b, max-pool-size: 1}]}}",
+        );
+        let pool = create_pools(&config).unwrap().remove("a").unwrap().remove(0).pool;
+        let _held = checkout(&pool).await.unwrap();
+
+        let ticks = Arc::new(AtomicUsize::new(0));
+        let ticker = tokio::spawn({
+            let ticks = ticks.clone();
+            async move {
+                loop {
+                    tokio::time::sleep(Duration::from_millis(10)).await;
+                    ticks.fetch_add(1, Ordering::Relaxed);
+                }
+            }
+        });
+        let started = Instant::now();
+        assert!(matches!(checkout(&pool).await, Err(ProxyError::PoolUnavailable(_))));
+        assert!(started.elapsed() >= Duration::from_millis(300));
+        assert!(ticks.load(Ordering::Relaxed) > 5);
+        ticker.abort();
+    }
+}
@@ -1,3 +1,4 @@
 pub(crate) mod minio_parser;
 pub(crate) mod minio_pool;
+pub(crate) mod presign_cache;
 pub(crate) mod r2d2_minio;
\ No newline at end of file
@@ -0,0 +1,178 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use lazy_static::lazy_static;
+use reqwest::Method;
+use serde::Serialize;
+
+// 缓存的预签名地址总数上限，超过时先清理已过期的，仍然超过则全部清空
+const MAX_LINKS: usize = 100_000;
+// 在过期前提前刷新，取有效期的 1/10，最少 1 秒，最多 5 分钟
+const MAX_REFRESH_MARGIN_SECS: u64 = 300;
+// 有效期短于该值时不缓存，提前量太短，客户端拿到的地址可能很快过期
+pub(crate) const MIN_CACHED_EXPIRY_SECS: u64 = 10;
+
+// (power, 实例 endpoint, 桶, 存储中的 key, 请求方法)，地址与签名的实例绑定，实例不可用后不会再命中
+pub type PresignKey = (String, String, String, String, Method);
+
+struct CachedLink {
+    url: String,
+    refresh_at: Instant,
+}
+
+// 按 power 统计命中次数和生成地址的耗时
+#[derive(Default, Clone)]
+struct PresignStats {
+    hits: u64,
+    misses: u64,
+    hit_nanos: u128,
+    sign_nanos: u128,
+}
+
+// 管理接口展示的预签名统计，耗时单位为微秒
+#[derive(Serialize, Debug)]
+pub struct PresignStatus {
+    power: String,
+    cached: usize,
+    hits: u64,
+    misses: u64,
+    avg_hit_us: u64,
+    avg_sign_us: u64,
+}
+
+lazy_static! {
+    static ref LINKS: Mutex<HashMap<PresignKey, CachedLink>> = Mutex::new(HashMap::new());
+    static ref STATS: Mutex<HashMap<String, PresignStats>> = Mutex::new(HashMap::new());
+}
+
+pub fn get(key: &PresignKey) -> Option<String> {
+    let links = LINKS.lock().unwrap_or_else(|e| e.into_inner());
+    links
+        .get(key)
+        .filter(|link| link.refresh_at > Instant::now())
+        .map(|link| link.url.clone())
+}
+
+pub fn insert(key: PresignKey, url: String, expiry_secs: u32) {
+    let expiry = expiry_secs as u64;
+    if expiry < MIN_CACHED_EXPIRY_SECS {
+        return;
+    }
+    let margin = (expiry / 10).clamp(1, MAX_REFRESH_MARGIN_SECS);
+    let refresh_at = Instant::now() + Duration::from_secs(expiry - margin);
+
+    let mut links = LINKS.lock().unwrap_or_else(|e| e.into_inner());
+    if links.len() >= MAX_LINKS {
+        let now = Instant::now();
+        links.retain(|_, link| link.refresh_at > now);
+        if links.len() >= MAX_LINKS {
+            links.clear();
+        }
+    }
+    links.insert(key, CachedLink { url, refresh_at });
+}
+
+// 记录一次生成预签名地址的耗时，hit 表示使用了缓存
+pub fn record(power: &str, hit: bool, elapsed: Duration) {
+    let mut stats = STATS.lock().unwrap_or_else(|e| e.into_inner());
+    let entry = stats.entry(power.to_string()).or_default();
+    if hit {
+        entry.hits += 1;
+        entry.hit_nanos += elapsed.as_nanos();
+    } else {
+        entry.misses += 1;
+        entry.sign_nanos += elapsed.as_nanos();
+    }
+}
+
+// 清空缓存的地址，重新加载配置后凭证和实例可能已经变化
+pub fn flush() -> usize {
+    let mut links = LINKS.lock().unwrap_or_else(|e| e.into_inner());
+    let count = links.len();
+    links.clear();
+    count
+}
+
+pub fn stats() -> Vec<PresignStatus> {
+    let mut cached: HashMap<String, usize> = HashMap::new();
+    for key in LINKS.lock().unwrap_or_else(|e| e.into_inner()).keys() {
+        *cached.entry(key.0.clone()).or_default() += 1;
+    }
+    let average = |nanos: u128, count: u64| if count == 0 { 0 } else { (nanos / count as u128 / 1000) as u64 };
+    let mut statuses: Vec<PresignStatus> = STATS
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .iter()
+        .map(|(power, stats)| PresignStatus {
+            power: power.clone(),
+            cached: cached.get(power).copied().unwrap_or_default(),
+            hits: stats.hits,
+            misses: stats.misses,
+            avg_hit_us: average(stats.hit_nanos, stats.hits),
+            avg_sign_us: average(stats.sign_nanos, stats.misses),
+        })
+        .collect();
+    statuses.sort_by(|a, b| a.power.cmp(&b.power));
+    statuses
+}
+
+#[cfg(test)]
+mod tests {
+    use minio::s3::args::GetPresignedObjectUrlArgs;
+    use minio::s3::client::Client;
+    use minio::s3::creds::StaticProvider;
+    use minio::s3::http::BaseUrl;
+
+    use super::*;
+
+    fn key(object: &str) -> PresignKey {
+        ("presign-test".to_string(), "127.0.0.1:9000".to_string(), "bkt".to_string(), object.to_string(), Method::GET)
+    }
+
+    #[test]
+    fn short_expiry_is_not_cached() {
+        insert(key("short"), "http://short".to_string(), 9);
+        assert_eq!(get(&key("short")), None);
+
+        let before = Instant::now();
+        insert(key("ten"), "http://ten".to_string(), 10);
+        assert_eq!(get(&key("ten")).as_deref(), Some("http://ten"));
+        // 提前量至少 1 秒
+        let refresh_at = LINKS.lock().unwrap().get(&key("ten")).unwrap().refresh_at;
+        assert!(refresh_at <= before + Duration::from_secs(9) + Duration::from_millis(100));
+    }
+
+    // 缓存命中返回同一个地址并计入命中次数，耗时只输出不比较，`cargo test presign -- --nocapture` 查看具体数值
+    #[tokio::test]
+    async fn cached_lookup_returns_signed_url() {
+        const ROUNDS: u32 = 500;
+        const POWER: &str = "presign-stats-test";
+        let key = || (POWER.to_string(), "127.0.0.1:9000".to_string(), "bkt".to_string(), "dir/a.txt".to_string(), Method::GET);
+        let mut base_url: BaseUrl = "http://127.0.0.1:9000".parse().unwrap();
+        base_url.region = "us-east-1".to_string();
+        let provider = StaticProvider::new("AKTEST", "secret-test", None);
+        let client = Client::new(base_url, Some(Box::new(provider)), None, None).unwrap();
+        let mut args = GetPresignedObjectUrlArgs::new("bkt", "dir/a.txt", Method::GET).unwrap();
+        args.expiry_seconds = Some(3600);
+
+        assert_eq!(get(&key()), None);
+        let started = Instant::now();
+        let url = client.get_presigned_object_url(&args).await.unwrap().url;
+        let sign = started.elapsed();
+        record(POWER, false, sign);
+        insert(key(), url.clone(), 3600);
+
+        let started = Instant::now();
+        for _ in 0..ROUNDS {
+            let lookup = Instant::now();
+            assert_eq!(get(&key()).as_deref(), Some(url.as_str()));
+            record(POWER, true, lookup.elapsed());
+        }
+        let hit = started.elapsed() / ROUNDS;
+        println!("presign: sign {:?}, cached {:?}/op", sign, hit);
+
+        let status = stats().into_iter().find(|status| status.power == POWER).unwrap();
+        assert_eq!((status.cached, status.hits, status.misses), (1, ROUNDS as u64, 1));
+    }
+}
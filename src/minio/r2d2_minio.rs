@@ -1,21 +1,33 @@
+use std::path::Path;
+
 use minio::s3::client::Client;
 use minio::s3::creds::StaticProvider;
 use minio::s3::error::Error as MinioError;
+use minio::s3::http::BaseUrl;
 use r2d2::ManageConnection;
 
+use crate::config::minio_config::{Addressing, MinioConfig};
+
 pub(crate) struct MinioConnectionManager {
-    endpoint: String,
-    access_key: String,
-    secret_key: String,
+    config: MinioConfig,
 }
 
 impl MinioConnectionManager {
-    pub fn new(endpoint: String, access_key: String, secret_key: String) -> Self {
-        MinioConnectionManager {
-            endpoint,
-            access_key,
-            secret_key,
+    pub fn new(config: MinioConfig) -> Self {
+        MinioConnectionManager { config }
+    }
+
+    // 在解析后的 endpoint 上应用 region、addressing 配置
+    fn configure(&self, mut base_url: BaseUrl) -> BaseUrl {
+        if let Some(region) = &self.config.region {
+            base_url.region = region.clone();
+        }
+        match self.config.addressing {
+            Addressing::Auto => {}
+            Addressing::Path => base_url.virtual_style = false,
+            Addressing::Virtual => base_url.virtual_style = true,
         }
+        base_url
     }
 }
 
@@ -23,24 +35,219 @@ impl ManageConnection for MinioConnectionManager {
     type Connection = Client;
     type Error = MinioError;
 
+    // minio 0.1 的 ClientBuilder 自行创建 reqwest 客户端，不能设置连接超时；
+    // 取客户端受连接池 connection_timeout 限制，读取对象和健康检查由调用方按 fetch.header-timeout-ms 限时
     fn connect(&self) -> Result<Self::Connection, Self::Error> {
-        let base_url = self.endpoint.parse()
-            .map_err(|e| MinioError::from(e))?;  // 修改这里
-        let provider = StaticProvider::new(&self.access_key, &self.secret_key, None);
-        let client = Client::new(base_url, Some(Box::new(provider)), None, None)
-            .map_err(|e| MinioError::from(e))?;  // 如果需要，修改这里
+        let base_url = self.configure(self.config.endpoint.parse()?);
+        let session_token = self.config.session_token.as_ref().map(|token| token.expose());
+        let provider = StaticProvider::new(&self.config.access_key, self.config.secret_key.expose(), session_token);
+        let client = Client::new(
+            base_url,
+            Some(Box::new(provider)),
+            self.config.ca_cert.as_deref().map(Path::new),
+            None,
+        )?;
         Ok(client)
     }
 
 
-    fn is_valid(&self, conn: &mut Self::Connection) -> Result<(), Self::Error> {
+    fn is_valid(&self, _conn: &mut Self::Connection) -> Result<(), Self::Error> {
         // Implement logic to verify connection is still valid
         Ok(())
     }
 
-    fn has_broken(&self, conn: &mut Self::Connection) -> bool {
+    fn has_broken(&self, _conn: &mut Self::Connection) -> bool {
         // Implement logic to check if connection is broken
         false
     }
 }
 
+// 转发预签名请求的客户端也要信任各 MinIO 配置的 CA 证书
+pub fn trusted_certificates<'a>(configs: impl Iterator<Item = &'a MinioConfig>) -> Vec<reqwest::Certificate> {
+    let mut certificates = Vec::new();
+    for file in configs.filter_map(|config| config.ca_cert.as_deref()) {
+        match std::fs::read(file).map_err(|e| e.to_string()).and_then(|pem| {
+            reqwest::Certificate::from_pem(&pem).map_err(|e| e.to_string())
+        }) {
+            Ok(certificate) => certificates.push(certificate),
+            Err(e) => log::warn!("Failed to load ca-cert {}: {}", file, e),
+        }
+    }
+    certificates
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use minio::s3::args::{GetObjectArgs, ListBucketsArgs, StatObjectArgs};
+    use minio::s3::utils::Multimap;
+    use warp::http::{HeaderMap, Method, StatusCode};
+    use warp::Filter;
+
+    use super::*;
+    use crate::s3::sigv4;
+
+    const ACCESS_KEY: &str = "AKTEST";
+    const SECRET_KEY: &str = "sekrit123";
+    const SESSION_TOKEN: &str = "token-abc";
+    // 未配置 region 时 stand-in 通过 GetBucketLocation 返回的区域
+    const BUCKET_REGION: &str = "ap-south-1";
+    const BODY: &str = "hello";
+
+    // stand-in 收到的一次请求
+    #[derive(Debug, Clone)]
+    struct Recorded {
+        host: String,
+        path: String,
+        query: String,
+        region: String,
+        signed_headers: Vec<String>,
+        security_token: Option<String>,
+    }
+
+    fn config(yaml: &str) -> MinioConfig {
+        serde_yaml::from_str(yaml).unwrap()
+    }
+
+    // 读取 Authorization 中 key= 之后到 , 为止的值
+    fn auth_param<'a>(authorization: &'a str, key: &str) -> &'a str {
+        let start = authorization.find(key).map(|index| index + key.len()).unwrap_or(authorization.len());
+        authorization[start..].split(',').next().unwrap_or_default()
+    }
+
+    fn xml(body: String) -> warp::http::Response<String> {
+        warp::http::Response::builder().header("content-type", "application/xml").body(body).unwrap()
+    }
+
+    // 本地 S3 stand-in：用本服务 S3 接口的 SigV4 校验逐个验证签名，记录请求，支持 ListBuckets、GetBucketLocation、HEAD/GET 对象
+    async fn stand_in() -> (u16, Arc<Mutex<Vec<Recorded>>>) {
+        let recorded = Arc::new(Mutex::new(Vec::new()));
+        let log = recorded.clone();
+        let route = warp::path::full()
+            .and(warp::query::raw().or(warp::any().map(String::new)).unify())
+            .and(warp::method())
+            .and(warp::header::headers_cloned())
+            .map(move |path: warp::path::FullPath, query: String, method: Method, headers: HeaderMap| {
+                let header = |name: &str| headers.get(name).and_then(|v| v.to_str().ok()).map(str::to_string);
+                let verified = sigv4::parse_authorization(&headers)
+                    .map_err(|e| format!("{:?}", e))
+                    .and_then(|auth| sigv4::verify(&auth, SECRET_KEY, &method, path.as_str(), &query, &headers).map_err(|e| format!("{:?}", e)));
+                if let Err(e) = verified {
+                    let body = format!("<Error><Code>SignatureDoesNotMatch</Code><Message>{}</Message></Error>", e);
+                    let mut response = xml(body);
+                    *response.status_mut() = StatusCode::FORBIDDEN;
+                    return response;
+                }
+                let authorization = header("authorization").unwrap_or_default();
+                let credential: Vec<&str> = auth_param(&authorization, "Credential=").split('/').collect();
+                log.lock().unwrap().push(Recorded {
+                    host: header("host").unwrap_or_default(),
+                    path: path.as_str().to_string(),
+                    query: query.clone(),
+                    region: credential.get(2).unwrap_or(&"").to_string(),
+                    signed_headers: auth_param(&authorization, "SignedHeaders=").split(';').map(str::to_string).collect(),
+                    security_token: header("x-amz-security-token"),
+                });
+
+                if path.as_str() == "/" {
+                    return xml("<ListAllMyBucketsResult><Buckets><Bucket><Name>bkt</Name><CreationDate>1970-01-01T00:00:00.000Z</CreationDate></Bucket></Buckets></ListAllMyBucketsResult>".to_string());
+                }
+                if query.contains("location") {
+                    return xml(format!("<LocationConstraint>{}</LocationConstraint>", BUCKET_REGION));
+                }
+                warp::http::Response::builder()
+                    .header("content-length", BODY.len())
+                    .header("etag", "\"abc\"")
+                    .header("last-modified", "Mon, 01 Jan 2024 00:00:00 GMT")
+                    .body(if method == Method::HEAD { String::new() } else { BODY.to_string() })
+                    .unwrap()
+            });
+        let (addr, server) = warp::serve(route).bind_ephemeral(([127, 0, 0, 1], 0));
+        tokio::spawn(server);
+        (addr.port(), recorded)
+    }
+
+    // 各 addressing 下实际请求的域名和路径，AWS 域名会带上 region；virtual 方式需要桶名子域名能够解析，只验证生成的地址
+    #[test]
+    fn addressing_builds_expected_urls() {
+        let cases = [
+            ("http://127.0.0.1:9000", "auto", "127.0.0.1", "/bkt/a.txt"),
+            ("http://127.0.0.1:9000", "path", "127.0.0.1", "/bkt/a.txt"),
+            ("https://minio.internal", "auto", "minio.internal", "/bkt/a.txt"),
+            ("https://minio.internal", "virtual", "bkt.minio.internal", "/a.txt"),
+            ("https://cos.ap-guangzhou.myqcloud.com", "virtual", "bkt.cos.ap-guangzhou.myqcloud.com", "/a.txt"),
+            ("https://s3.amazonaws.com", "auto", "bkt.s3.us-east-1.amazonaws.com", "/a.txt"),
+            ("https://s3.amazonaws.com", "path", "s3.us-east-1.amazonaws.com", "/bkt/a.txt"),
+        ];
+        for (endpoint, addressing, host, path) in cases {
+            let yaml = format!(
+                "{{endpoint: '{}', access-key: {}, secret-key: {}, region: us-east-1, addressing: {}}}",
+                endpoint, ACCESS_KEY, SECRET_KEY, addressing
+            );
+            let manager = MinioConnectionManager::new(config(&yaml));
+            let base_url = manager.configure(endpoint.parse().unwrap());
+            let url = base_url
+                .build_url(&Method::GET, &"us-east-1".to_string(), &Multimap::new(), Some("bkt"), Some("a.txt"))
+                .unwrap();
+            assert_eq!((url.host.as_str(), url.path.as_str()), (host, path), "{} {}", endpoint, addressing);
+        }
+    }
+
+    #[tokio::test]
+    async fn region_and_session_token_against_stand_in() {
+        let (port, recorded) = stand_in().await;
+        for region in [Some("eu-west-3"), None] {
+            for session_token in [Some(SESSION_TOKEN), None] {
+                let case = format!("region {:?}, session token {:?}", region, session_token);
+                let mut yaml = format!(
+                    "{{endpoint: 'http://127.0.0.1:{}', access-key: {}, secret-key: {}, addressing: path",
+                    port, ACCESS_KEY, SECRET_KEY
+                );
+                if let Some(region) = region {
+                    yaml.push_str(&format!(", region: {}", region));
+                }
+                if let Some(token) = session_token {
+                    yaml.push_str(&format!(", session-token: {}", token));
+                }
+                yaml.push('}');
+                recorded.lock().unwrap().clear();
+
+                let client = MinioConnectionManager::new(config(&yaml)).connect().unwrap();
+                client.list_buckets(&ListBucketsArgs::default()).await.expect(&case);
+                let stat = client.stat_object(&StatObjectArgs::new("bkt", "dir/a.txt").unwrap()).await.expect(&case);
+                assert_eq!(stat.size, BODY.len(), "{}", case);
+                let response = client.get_object(&GetObjectArgs::new("bkt", "dir/a.txt").unwrap()).await.expect(&case);
+                assert_eq!(response.text().await.unwrap(), BODY, "{}", case);
+
+                let requests = recorded.lock().unwrap().clone();
+                // 未配置 region 时先查询桶所在区域，之后的请求都使用该区域签名
+                let location_requests = requests.iter().filter(|request| request.query.contains("location")).count();
+                assert_eq!(location_requests, usize::from(region.is_none()), "{}", case);
+                let object_requests: Vec<&Recorded> = requests.iter().filter(|request| request.path == "/bkt/dir/a.txt").collect();
+                assert_eq!(object_requests.len(), 2, "{}", case);
+                for request in object_requests {
+                    assert_eq!(request.host, format!("127.0.0.1:{}", port), "{}", case);
+                    assert_eq!(request.region, region.unwrap_or(BUCKET_REGION), "{}", case);
+                }
+                for request in &requests {
+                    assert_eq!(request.security_token.as_deref(), session_token, "{}", case);
+                    let signed = request.signed_headers.iter().any(|name| name == "x-amz-security-token");
+                    assert_eq!(signed, session_token.is_some(), "{}", case);
+                }
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn wrong_secret_is_rejected_by_stand_in() {
+        let (port, _) = stand_in().await;
+        let yaml = format!(
+            "{{endpoint: 'http://127.0.0.1:{}', access-key: {}, secret-key: wrong, region: us-east-1, addressing: path}}",
+            port, ACCESS_KEY
+        );
+        let client = MinioConnectionManager::new(config(&yaml)).connect().unwrap();
+        let error = client.list_buckets(&ListBucketsArgs::default()).await.unwrap_err();
+        assert!(error.to_string().contains("SignatureDoesNotMatch"), "{}", error);
+    }
+}
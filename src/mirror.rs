@@ -0,0 +1,118 @@
+use std::collections::HashSet;
+use std::sync::Mutex;
+
+use lazy_static::lazy_static;
+use tracing::Instrument;
+use warp::http::{HeaderMap, StatusCode};
+
+use crate::backend::{self, ObjectResponse};
+use crate::config;
+use crate::error::ProxyError;
+use crate::minio::minio_parser;
+use crate::rewrite;
+
+// 回写对象的默认大小上限
+const DEFAULT_MAX_COPY_SIZE: u64 = 64 * 1024 * 1024;
+
+lazy_static! {
+    // 正在回写的对象 (power, bucket, key)，避免并发请求重复复制
+    static ref COPYING: Mutex<HashSet<(String, String, String)>> = Mutex::new(HashSet::new());
+}
+
+// 读取对象，当前 power 返回 404 时按 mirror.sources 的顺序回源
+// 每个来源按自己的桶和改写规则解析 key，来源自身的 mirror 配置不会生效
+pub async fn get(
+    config_key: &str,
+    route_bucket: Option<&str>,
+    object_key: &str,
+    headers: &HeaderMap,
+) -> Result<ObjectResponse, ProxyError> {
+    let stored_key = rewrite::rewrite_object_key(config_key, object_key);
+    let bucket = minio_parser::bucket_for(config_key, route_bucket).await;
+    let not_found = match backend::for_power(config_key)?.get(&bucket, &stored_key, headers).await {
+        Err(e) if e.status() == StatusCode::NOT_FOUND => e,
+        result => return result,
+    };
+
+    let config = config::current();
+    let mirror = match config.power_config(config_key).and_then(|power| power.mirror.as_ref()) {
+        Some(mirror) => mirror,
+        None => return Err(not_found),
+    };
+    for source in &mirror.sources {
+        let source_key = rewrite::rewrite_object_key(source, object_key);
+        let source_bucket = minio_parser::bucket_for(source, route_bucket).await;
+        // 来源不可用时直接返回错误，不能当作对象不存在
+        let response = match backend::for_power(source)?.get(&source_bucket, &source_key, headers).await {
+            Err(e) if e.status() == StatusCode::NOT_FOUND => continue,
+            result => result?,
+        };
+        log::info!("Mirror hit: {}/{} of {} found in {}", bucket, stored_key, config_key, source);
+        if mirror.read_through {
+            let copy = CopyTask {
+                config_key: config_key.to_string(),
+                bucket,
+                key: stored_key,
+                source: source.clone(),
+                source_bucket,
+                source_key,
+                max_size: mirror.max_copy_size.unwrap_or(DEFAULT_MAX_COPY_SIZE),
+            };
+            tokio::spawn(copy.run().instrument(tracing::info_span!("mirror_copy")));
+        }
+        return Ok(response);
+    }
+    Err(not_found)
+}
+
+// 把来源中的对象复制到当前 power
+struct CopyTask {
+    config_key: String,
+    bucket: String,
+    key: String,
+    source: String,
+    source_bucket: String,
+    source_key: String,
+    max_size: u64,
+}
+
+impl CopyTask {
+    async fn run(self) {
+        let id = (self.config_key.clone(), self.bucket.clone(), self.key.clone());
+        if !COPYING.lock().unwrap_or_else(|e| e.into_inner()).insert(id.clone()) {
+            return;
+        }
+        match self.copy().await {
+            Ok(Some(size)) => log::info!("Mirror copied {}/{} ({} bytes) from {} to {}", self.bucket, self.key, size, self.source, self.config_key),
+            Ok(None) => log::info!("Mirror skipped copying {}/{} of {}: size unknown or larger than {}", self.bucket, self.key, self.config_key, self.max_size),
+            Err(e) => log::warn!("Mirror failed to copy {}/{} from {} to {}: {}", self.bucket, self.key, self.source, self.config_key, e),
+        }
+        COPYING.lock().unwrap_or_else(|e| e.into_inner()).remove(&id);
+    }
+
+    // 返回复制的字节数，对象超过大小上限时返回 None
+    async fn copy(&self) -> Result<Option<u64>, ProxyError> {
+        // 读取完整对象，不带客户端的 Range 和条件请求头
+        let response = backend::for_power(&self.source)?
+            .get(&self.source_bucket, &self.source_key, &HeaderMap::new())
+            .await?;
+        let size = response
+            .headers
+            .get("content-length")
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.parse::<u64>().ok());
+        if size.is_none_or(|size| size > self.max_size) {
+            return Ok(None);
+        }
+
+        let put_headers = backend::stored_headers(&response.headers);
+        let body = warp::hyper::body::to_bytes(response.body)
+            .await
+            .map_err(|e| ProxyError::Upstream(e.to_string()))?;
+        let size = body.len() as u64;
+        backend::for_power(&self.config_key)?
+            .put(&self.bucket, &self.key, body, put_headers)
+            .await?;
+        Ok(Some(size))
+    }
+}
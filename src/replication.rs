@@ -0,0 +1,288 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use warp::http::{HeaderMap, StatusCode};
+
+use crate::backend;
+use crate::cache::{self, RedisPool};
+use crate::config;
+use crate::config::replication_config::ReplicationConfig;
+use crate::error::ProxyError;
+use crate::minio::minio_parser;
+use crate::rewrite;
+
+// Redis 键前缀，power 放在 {} 中作为 hash tag，集群模式下队列和失败记录在同一个槽
+const KEY_PREFIX: &str = "warp_minio:replication:";
+// 每轮每个 power 最多领取的任务数
+const BATCH_SIZE: usize = 32;
+// 领取任务后的租约，进程退出等原因未完成的任务在租约结束后会被重新领取
+const LEASE_MS: u64 = 5 * 60 * 1000;
+const DEFAULT_MAX_ATTEMPTS: u32 = 10;
+const DEFAULT_RETRY_INTERVAL_MS: u64 = 5000;
+const MAX_RETRY_INTERVAL_MS: u64 = 10 * 60 * 1000;
+// 保留的失败记录条数
+const FAILED_HISTORY: isize = 100;
+// 计算复制延迟时最多读取的排队任务数
+const LAG_SAMPLE: isize = 1000;
+
+// 只领取已到执行时间的任务，领取时把分数推迟到租约结束，多个实例不会重复处理
+const CLAIM_SCRIPT: &str = "local score = redis.call('ZSCORE', KEYS[1], ARGV[1]) \
+if score and tonumber(score) <= tonumber(ARGV[2]) then redis.call('ZADD', KEYS[1], ARGV[3], ARGV[1]) return 1 end \
+return 0";
+
+// 队列中的复制任务，序列化后作为有序集合的成员，分数为下次执行的时间(毫秒)
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct Job {
+    id: String,
+    target: String,
+    // path 模式下请求路径中的桶
+    bucket: Option<String>,
+    // 公开的对象 key，源和目标各自按改写规则解析
+    key: String,
+    // 为 true 时 key 是存储中的 key，源和目标都不再改写
+    #[serde(default)]
+    stored: bool,
+    enqueued_at: u64,
+    attempts: u32,
+    last_error: Option<String>,
+}
+
+// 本进程内按 (power, target) 统计的复制结果
+#[derive(Serialize, Debug, Default, Clone)]
+struct Counters {
+    replicated: u64,
+    retried: u64,
+    failed: u64,
+    enqueue_errors: u64,
+    last_success_at: Option<u64>,
+    last_error: Option<String>,
+}
+
+lazy_static! {
+    static ref COUNTERS: Mutex<HashMap<(String, String), Counters>> = Mutex::new(HashMap::new());
+}
+
+fn queue_key(power: &str) -> String {
+    format!("{}{{{}}}:queue", KEY_PREFIX, power)
+}
+
+fn failed_key(power: &str) -> String {
+    format!("{}{{{}}}:failed", KEY_PREFIX, power)
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_millis() as u64).unwrap_or_default()
+}
+
+fn count(power: &str, target: &str, f: impl FnOnce(&mut Counters)) {
+    let mut counters = COUNTERS.lock().unwrap_or_else(|e| e.into_inner());
+    f(counters.entry((power.to_string(), target.to_string())).or_default());
+}
+
+// 对象写入主存储后，为每个目标 power 加入复制任务，任务未能写入 Redis 时返回错误，由调用方拒绝这次写入
+pub async fn enqueue(power: &str, bucket: Option<&str>, key: &str, stored: bool) -> Result<(), ProxyError> {
+    let config = config::current();
+    let targets = match config.power_config(power).and_then(|p| p.replication.as_ref()) {
+        Some(replication) => replication.targets.clone(),
+        None => return Ok(()),
+    };
+    let pool = match cache::get_pinned_redis_pool(power) {
+        Ok(pool) => pool,
+        Err(e) => {
+            log::error!("Replication of {}/{} is not queued: {}", power, key, e);
+            for target in &targets {
+                count(power, target, |c| c.enqueue_errors += 1);
+            }
+            return Err(ProxyError::PoolUnavailable(format!("Replication queue is unavailable: {}", e)));
+        }
+    };
+
+    let now = now_ms();
+    let mut failure = None;
+    for target in targets {
+        let job = Job {
+            id: format!("{:016x}", rand::random::<u64>()),
+            target: target.clone(),
+            bucket: bucket.map(str::to_string),
+            key: key.to_string(),
+            stored,
+            enqueued_at: now,
+            attempts: 0,
+            last_error: None,
+        };
+        let member = serde_json::to_string(&job).unwrap_or_default();
+        let result: redis::RedisResult<i64> = pool.command(redis::cmd("ZADD").arg(queue_key(power)).arg(now).arg(member)).await;
+        if let Err(e) = result {
+            log::error!("Failed to queue replication of {} from {} to {}: {}", key, power, target, e);
+            count(power, &target, |c| c.enqueue_errors += 1);
+            failure = Some(ProxyError::PoolUnavailable(format!("Failed to queue replication to {}: {}", target, e)));
+        }
+    }
+    failure.map_or(Ok(()), Err)
+}
+
+// 后台任务，每秒处理一次各 power 已到期的复制任务
+pub async fn run() {
+    let mut interval = tokio::time::interval(Duration::from_secs(1));
+    loop {
+        interval.tick().await;
+        let config = config::current();
+        let powers: Vec<(String, ReplicationConfig)> = config
+            .power
+            .iter()
+            .flatten()
+            .filter_map(|(key, power)| Some((key.clone(), power.replication.clone()?)))
+            .collect();
+        for (power, replication) in powers {
+            let pool = match cache::get_pinned_redis_pool(&power) {
+                Ok(pool) => pool,
+                Err(_) => continue,
+            };
+            if let Err(e) = process_due(&power, &replication, &pool).await {
+                log::warn!("Replication queue of {} is unavailable: {}", power, e);
+            }
+        }
+    }
+}
+
+async fn process_due(power: &str, replication: &ReplicationConfig, pool: &RedisPool) -> redis::RedisResult<()> {
+    let queue = queue_key(power);
+    let now = now_ms();
+    let members: Vec<String> = pool
+        .command(redis::cmd("ZRANGEBYSCORE").arg(&queue).arg("-inf").arg(now).arg("LIMIT").arg(0).arg(BATCH_SIZE))
+        .await?;
+    for member in members {
+        let claimed: i64 = pool
+            .command(redis::cmd("EVAL").arg(CLAIM_SCRIPT).arg(1).arg(&queue).arg(&member).arg(now).arg(now + LEASE_MS))
+            .await?;
+        if claimed == 0 {
+            continue;
+        }
+        let mut job: Job = match serde_json::from_str(&member) {
+            Ok(job) => job,
+            Err(e) => {
+                log::warn!("Dropping invalid replication job in {}: {}", power, e);
+                pool.command::<i64>(redis::cmd("ZREM").arg(&queue).arg(&member)).await?;
+                continue;
+            }
+        };
+
+        match replicate(power, &job).await {
+            Ok(()) => {
+                count(power, &job.target, |c| {
+                    c.replicated += 1;
+                    c.last_success_at = Some(now_ms());
+                });
+            }
+            Err(e) => {
+                job.attempts += 1;
+                job.last_error = Some(e.to_string());
+                count(power, &job.target, |c| c.last_error = Some(e.to_string()));
+                let updated = serde_json::to_string(&job).unwrap_or_default();
+                // 先写入新状态再删除旧任务，中途失败最多重复复制一次
+                if job.attempts >= replication.max_attempts.unwrap_or(DEFAULT_MAX_ATTEMPTS) {
+                    log::error!("Replication of {} from {} to {} failed after {} attempts: {}", job.key, power, job.target, job.attempts, e);
+                    let failed = failed_key(power);
+                    pool.command::<i64>(redis::cmd("LPUSH").arg(&failed).arg(&updated)).await?;
+                    pool.command::<()>(redis::cmd("LTRIM").arg(&failed).arg(0).arg(FAILED_HISTORY - 1)).await?;
+                    count(power, &job.target, |c| c.failed += 1);
+                } else {
+                    let interval = replication.retry_interval_ms.unwrap_or(DEFAULT_RETRY_INTERVAL_MS);
+                    let delay = interval.saturating_mul(1 << (job.attempts - 1).min(16)).min(MAX_RETRY_INTERVAL_MS);
+                    log::warn!("Replication of {} from {} to {} failed, retrying in {}ms: {}", job.key, power, job.target, delay, e);
+                    pool.command::<i64>(redis::cmd("ZADD").arg(&queue).arg(now_ms() + delay).arg(&updated)).await?;
+                    count(power, &job.target, |c| c.retried += 1);
+                }
+            }
+        }
+        pool.command::<i64>(redis::cmd("ZREM").arg(&queue).arg(&member)).await?;
+    }
+    Ok(())
+}
+
+// 从当前 power 读取完整对象写入目标 power，源对象已删除时视为完成
+async fn replicate(power: &str, job: &Job) -> Result<(), ProxyError> {
+    let object_key = |power: &str| if job.stored { job.key.clone() } else { rewrite::rewrite_object_key(power, &job.key) };
+    let source_key = object_key(power);
+    let source_bucket = minio_parser::bucket_for(power, job.bucket.as_deref()).await;
+    let response = match backend::for_power(power)?.get(&source_bucket, &source_key, &HeaderMap::new()).await {
+        Err(e) if e.status() == StatusCode::NOT_FOUND => {
+            log::info!("Skipping replication of {}/{} of {}: object no longer exists", source_bucket, source_key, power);
+            return Ok(());
+        }
+        result => result?,
+    };
+    let put_headers = backend::stored_headers(&response.headers);
+    let body = warp::hyper::body::to_bytes(response.body)
+        .await
+        .map_err(|e| ProxyError::Upstream(e.to_string()))?;
+
+    let target_key = object_key(&job.target);
+    let target_bucket = minio_parser::bucket_for(&job.target, job.bucket.as_deref()).await;
+    backend::for_power(&job.target)?
+        .put(&target_bucket, &target_key, body, put_headers)
+        .await?;
+    log::info!("Replicated {}/{} from {} to {}", target_bucket, target_key, power, job.target);
+    Ok(())
+}
+
+// 管理接口展示的复制状态：排队数量、最早任务的等待时间、失败记录和本进程的统计
+pub async fn status() -> Vec<serde_json::Value> {
+    let config = config::current();
+    let mut powers: Vec<(&String, &ReplicationConfig)> = config
+        .power
+        .iter()
+        .flatten()
+        .filter_map(|(key, power)| Some((key, power.replication.as_ref()?)))
+        .collect();
+    powers.sort_by(|a, b| a.0.cmp(b.0));
+
+    let counters = COUNTERS.lock().unwrap_or_else(|e| e.into_inner()).clone();
+    let mut statuses = Vec::with_capacity(powers.len());
+    for (power, replication) in powers {
+        let targets: Vec<serde_json::Value> = replication
+            .targets
+            .iter()
+            .map(|target| {
+                let counter = counters.get(&(power.clone(), target.clone())).cloned().unwrap_or_default();
+                json!({ "target": target, "counters": counter })
+            })
+            .collect();
+        let queue = match queue_status(power).await {
+            Ok(queue) => queue,
+            Err(e) => json!({ "error": e.to_string() }),
+        };
+        statuses.push(json!({ "power": power, "targets": targets, "queue": queue }));
+    }
+    statuses
+}
+
+async fn queue_status(power: &str) -> Result<serde_json::Value, String> {
+    let pool = cache::get_pinned_redis_pool(power)?;
+    let queue = queue_key(power);
+    let failed = failed_key(power);
+    let pending: u64 = pool.command(redis::cmd("ZCARD").arg(&queue)).await.map_err(|e| e.to_string())?;
+    let sample: Vec<String> = pool
+        .command(redis::cmd("ZRANGE").arg(&queue).arg(0).arg(LAG_SAMPLE - 1))
+        .await
+        .map_err(|e| e.to_string())?;
+    let jobs: Vec<Job> = sample.iter().filter_map(|member| serde_json::from_str(member).ok()).collect();
+    let oldest = jobs.iter().map(|job| job.enqueued_at).min();
+    let failed_total: u64 = pool.command(redis::cmd("LLEN").arg(&failed)).await.map_err(|e| e.to_string())?;
+    let recent: Vec<String> = pool
+        .command(redis::cmd("LRANGE").arg(&failed).arg(0).arg(19))
+        .await
+        .map_err(|e| e.to_string())?;
+    let recent: Vec<Job> = recent.iter().filter_map(|member| serde_json::from_str(member).ok()).collect();
+    Ok(json!({
+        "pending": pending,
+        "retrying": jobs.iter().filter(|job| job.attempts > 0).count(),
+        "lag_ms": oldest.map(|oldest| now_ms().saturating_sub(oldest)),
+        "failed": failed_total,
+        "recent_failures": recent,
+    }))
+}
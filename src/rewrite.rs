@@ -0,0 +1,172 @@
+use std::collections::HashMap;
+use std::sync::RwLock;
+
+use lazy_static::lazy_static;
+use regex::Regex;
+
+use crate::config;
+use crate::config::rewrite_config::RewriteRule;
+
+lazy_static! {
+    // 编译后的正则，按规则中的原始表达式缓存，重新加载配置后自然使用新的表达式
+    static ref COMPILED: RwLock<HashMap<String, Regex>> = RwLock::new(HashMap::new());
+}
+
+// 按 power 的改写规则把公开路径转换为对象 key，没有匹配的规则时原样返回
+pub fn rewrite_object_key(config_key: &str, object_key: &str) -> String {
+    let config = config::current();
+    match config.power_config(config_key).and_then(|power| power.rewrite.as_ref()) {
+        Some(rules) => apply_rules(rules, object_key),
+        None => object_key.to_string(),
+    }
+}
+
+fn apply_rules(rules: &[RewriteRule], object_key: &str) -> String {
+    for rule in rules {
+        if let Some(rewritten) = apply_rule(rule, object_key) {
+            // 捕获组可能拼出 `..`，这样的结果不使用，避免访问到公开路径之外的对象
+            if has_parent_segment(&rewritten) && !has_parent_segment(object_key) {
+                log::warn!("Rewrite {} -> {} is ignored, it contains `..`", object_key, rewritten);
+                return object_key.to_string();
+            }
+            log::debug!("Rewrite {} -> {}", object_key, rewritten);
+            return rewritten;
+        }
+    }
+    object_key.to_string()
+}
+
+pub fn has_parent_segment(key: &str) -> bool {
+    key.split('/').any(|segment| segment == "..")
+}
+
+fn apply_rule(rule: &RewriteRule, object_key: &str) -> Option<String> {
+    let mut key = object_key.trim_start_matches('/').to_string();
+
+    if let Some(prefix) = &rule.strip_prefix {
+        key = key.strip_prefix(prefix.trim_start_matches('/'))?.to_string();
+    }
+
+    let pattern = match (&rule.from, &rule.regex) {
+        (Some(from), _) => Some(template_regex(from)),
+        (None, Some(regex)) => Some(regex.clone()),
+        (None, None) => None,
+    };
+    if let Some(pattern) = pattern {
+        let regex = compiled(&pattern)?;
+        let captures = regex.captures(&key)?;
+        if let Some(to) = &rule.to {
+            let to = match &rule.from {
+                // 模板中的 {name} 转换为正则替换语法 ${name}
+                Some(_) => to.replace('$', "$$").replace('{', "${").replace("*}", "}"),
+                None => to.clone(),
+            };
+            let mut expanded = String::new();
+            captures.expand(&to, &mut expanded);
+            key = expanded;
+        }
+    }
+
+    if let Some(prefix) = &rule.add_prefix {
+        key = format!("{}{}", prefix.trim_start_matches('/'), key);
+    }
+    Some(key.trim_start_matches('/').to_string())
+}
+
+// 路径模板转换为正则：{name} 匹配一段路径，{name*} 匹配多段，其余字符按字面量匹配
+pub fn template_regex(template: &str) -> String {
+    let template = template.trim_start_matches('/');
+    let mut pattern = String::from("^");
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        pattern.push_str(&regex::escape(&rest[..start]));
+        let Some(end) = rest[start..].find('}') else {
+            pattern.push_str(&regex::escape(&rest[start..]));
+            rest = "";
+            break;
+        };
+        let name = &rest[start + 1..start + end];
+        match name.strip_suffix('*') {
+            Some(name) => pattern.push_str(&format!("(?P<{}>.+)", name)),
+            None => pattern.push_str(&format!("(?P<{}>[^/]+)", name)),
+        }
+        rest = &rest[start + end + 1..];
+    }
+    pattern.push_str(&regex::escape(rest));
+    pattern.push('$');
+    pattern
+}
+
+fn compiled(pattern: &str) -> Option<Regex> {
+    if let Some(regex) = COMPILED.read().unwrap_or_else(|e| e.into_inner()).get(pattern) {
+        return Some(regex.clone());
+    }
+    match Regex::new(pattern) {
+        Ok(regex) => {
+            COMPILED
+                .write()
+                .unwrap_or_else(|e| e.into_inner())
+                .insert(pattern.to_string(), regex.clone());
+            Some(regex)
+        }
+        Err(e) => {
+            log::error!("Invalid rewrite pattern {}: {}", pattern, e);
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rules(yaml: &str) -> Vec<RewriteRule> {
+        serde_yaml::from_str(yaml).unwrap()
+    }
+
+    #[test]
+    fn template_captures_are_substituted() {
+        let rules = rules("[{from: '/avatars/{uid}.png', to: 'users/{uid}/avatar.png'}, {from: 'docs/{rest*}', to: 'v2/{rest*}'}]");
+        assert_eq!(apply_rules(&rules, "avatars/42.png"), "users/42/avatar.png");
+        assert_eq!(apply_rules(&rules, "/avatars/42.png"), "users/42/avatar.png");
+        assert_eq!(apply_rules(&rules, "docs/a/b/c.md"), "v2/a/b/c.md");
+        // {uid} 只匹配一段路径
+        assert_eq!(apply_rules(&rules, "avatars/a/42.png"), "avatars/a/42.png");
+    }
+
+    #[test]
+    fn regex_captures_are_substituted() {
+        let rules = rules(r"[{regex: '^legacy/(\d+)/(?P<name>.*)$', to: 'archive/$1/${name}'}]");
+        assert_eq!(apply_rules(&rules, "legacy/2020/a/b.png"), "archive/2020/a/b.png");
+        assert_eq!(apply_rules(&rules, "legacy/x/b.png"), "legacy/x/b.png");
+    }
+
+    #[test]
+    fn unmatched_key_is_unchanged() {
+        let rules = rules("[{strip-prefix: static/, add-prefix: assets/}, {from: 'a/{x}', to: 'b/{x}'}]");
+        assert_eq!(apply_rule(&rules[0], "img/x.png"), None);
+        assert_eq!(apply_rule(&rules[1], "a/b/c"), None);
+        assert_eq!(apply_rules(&rules, "img/x.png"), "img/x.png");
+        assert_eq!(apply_rules(&rules, "static/x.png"), "assets/x.png");
+    }
+
+    #[test]
+    fn first_matching_rule_wins() {
+        let rules = rules("[{from: 'img/{name}', to: 'first/{name}'}, {strip-prefix: img/, add-prefix: second/}, {regex: '.*', to: third}]");
+        assert_eq!(apply_rules(&rules, "img/x.png"), "first/x.png");
+        assert_eq!(apply_rules(&rules, "img/a/x.png"), "second/a/x.png");
+        assert_eq!(apply_rules(&rules, "other.png"), "third");
+    }
+
+    #[test]
+    fn parent_segments_and_leading_slash_are_not_produced() {
+        let rules = rules("[{from: 'p/{a}/{b}', to: '{a}{b}/x'}, {regex: '^(.*)$', to: '/$1'}]");
+        // 两个捕获组拼出 `..` 时不改写
+        assert_eq!(apply_rules(&rules, "p/./."), "p/./.");
+        assert_eq!(apply_rules(&rules, "p/a/b"), "ab/x");
+        // 开头的 `/` 会被去掉
+        assert_eq!(apply_rules(&rules, "q.png"), "q.png");
+        assert!(has_parent_segment("a/../b"));
+        assert!(!has_parent_segment("a/..b/c"));
+    }
+}
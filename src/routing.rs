@@ -0,0 +1,152 @@
+use warp::{Filter, Rejection};
+
+use crate::config;
+use crate::config::power_config::BucketMode;
+use crate::config::warp_config::WarpConfig;
+use crate::error::ProxyError;
+
+// 请求解析后对应的 power 和对象
+#[derive(Debug)]
+pub struct Route {
+    pub(crate) config_key: String,
+    // path 模式下路径中的桶，fixed 模式为 None
+    pub(crate) bucket: Option<String>,
+    pub(crate) object_key: String,
+}
+
+// 先按 Host 匹配 power，匹配到时整个路径都是对象 key；否则按 match-prefix 后的第一段路径匹配
+pub fn resolve(config: &WarpConfig, host: Option<&str>, request_uri: &str) -> Result<Route, ProxyError> {
+    if let Some(config_key) = host.and_then(|host| config.power_by_host(host)) {
+        return select_bucket(config, config_key, request_uri.trim_start_matches('/'));
+    }
+
+    let url_prefix = config
+        .match_prefix
+        .as_deref()
+        .unwrap_or(config::URL_PREFIX);
+
+    let bucket_path = request_uri
+        .strip_prefix(url_prefix)
+        .ok_or_else(|| ProxyError::BadRequest("URI does not start with the expected prefix".to_string()))?;
+    let path = bucket_path.trim_start_matches('/');
+    let mut parts = path.splitn(2, '/');
+    let config_key = parts.next().unwrap_or("");
+    select_bucket(config, config_key, parts.next().unwrap_or(""))
+}
+
+// path 模式下从路径中取出桶名，并检查是否在允许的列表中
+fn select_bucket(config: &WarpConfig, config_key: &str, path: &str) -> Result<Route, ProxyError> {
+    let power = match config.power_config(config_key) {
+        Some(power) if power.bucket_mode == BucketMode::Path => power,
+        _ => {
+            return Ok(Route {
+                config_key: config_key.to_string(),
+                bucket: None,
+                object_key: path.to_string(),
+            });
+        }
+    };
+
+    let (bucket, object_key) = path
+        .split_once('/')
+        .filter(|(bucket, key)| !bucket.is_empty() && !key.is_empty())
+        .ok_or_else(|| ProxyError::BadRequest("Path must be /{config_key}/{bucket}/{object_key}".to_string()))?;
+    if !power.buckets.iter().flatten().any(|allowed| allowed == bucket) {
+        return Err(ProxyError::AccessDenied(format!("Bucket {} is not allowed for {}", bucket, config_key)));
+    }
+    Ok(Route {
+        config_key: config_key.to_string(),
+        bucket: Some(bucket.to_string()),
+        object_key: object_key.to_string(),
+    })
+}
+
+// Host 没有匹配到 power 时通过；匹配到时整个路径都是对象 key，探针等固定路径不能占用
+pub fn unrouted_host() -> impl Filter<Extract = (), Error = Rejection> + Clone {
+    warp::header::optional::<String>("host")
+        .and_then(|host: Option<String>| async move {
+            match host.as_deref().and_then(|host| config::current().power_by_host(host).map(str::to_string)) {
+                Some(_) => Err(warp::reject::not_found()),
+                None => Ok(()),
+            }
+        })
+        .untuple_one()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(yaml: &str) -> WarpConfig {
+        serde_yaml::from_str(yaml).unwrap()
+    }
+
+    fn route(config: &WarpConfig, host: Option<&str>, uri: &str) -> (String, Option<String>, String) {
+        let route = resolve(config, host, uri).unwrap();
+        (route.config_key, route.bucket, route.object_key)
+    }
+
+    fn fixed(config_key: &str, object_key: &str) -> (String, Option<String>, String) {
+        (config_key.to_string(), None, object_key.to_string())
+    }
+
+    const HOSTS: &str = "
+power:
+  exact: {hosts: [files.a.com]}
+  wild: {hosts: ['*.a.com']}
+  deep: {hosts: ['*.img.a.com']}
+  atom: {bucket-name: atom}
+";
+
+    #[test]
+    fn exact_host_wins_over_wildcards() {
+        let config = config(HOSTS);
+        assert_eq!(route(&config, Some("files.a.com"), "/x.png"), fixed("exact", "x.png"));
+        assert_eq!(route(&config, Some("FILES.A.COM"), "/x.png"), fixed("exact", "x.png"));
+        assert_eq!(route(&config, Some("cdn.a.com"), "/x.png"), fixed("wild", "x.png"));
+        assert_eq!(route(&config, Some("x.img.a.com"), "/x.png"), fixed("deep", "x.png"));
+    }
+
+    #[test]
+    fn equal_wildcards_break_ties_by_power_name() {
+        // 每次解析得到的 HashMap 顺序不同，结果应当一致
+        for _ in 0..20 {
+            let config = config("power: {zeta: {hosts: ['*.a.com']}, alpha: {hosts: ['a.*.com']}, mid: {hosts: ['*.*.com']}}");
+            assert_eq!(config.power_by_host("a.a.com"), Some("alpha"));
+        }
+    }
+
+    #[test]
+    fn port_is_stripped_from_host() {
+        let config = config(HOSTS);
+        assert_eq!(route(&config, Some("files.a.com:8080"), "/x.png"), fixed("exact", "x.png"));
+        assert_eq!(config.power_by_host("[::1]:8080"), None);
+    }
+
+    #[test]
+    fn host_takes_precedence_over_prefix() {
+        let config = config(HOSTS);
+        // Host 匹配时整个路径都是对象 key，不再按 match-prefix 和第一段路径匹配
+        assert_eq!(route(&config, Some("files.a.com"), "/minio/atom/x.png"), fixed("exact", "minio/atom/x.png"));
+        assert_eq!(route(&config, Some("other.com"), "/minio/atom/x.png"), fixed("atom", "x.png"));
+        assert_eq!(route(&config, None, "/minio/atom/dir/x.png"), fixed("atom", "dir/x.png"));
+        assert!(matches!(resolve(&config, None, "/other/atom/x.png"), Err(ProxyError::BadRequest(_))));
+    }
+
+    #[test]
+    fn dav_paths_on_host_routed_powers_are_objects() {
+        let config = config(HOSTS);
+        assert_eq!(route(&config, Some("files.a.com"), "/dav/exact/x.png"), fixed("exact", "dav/exact/x.png"));
+        assert_eq!(route(&config, Some("cdn.a.com"), "/dav/"), fixed("wild", "dav/"));
+    }
+
+    #[test]
+    fn path_mode_checks_bucket() {
+        let config = config("power: {multi: {bucket-mode: path, buckets: [a, b], hosts: [m.a.com]}}");
+        let expected = ("multi".to_string(), Some("b".to_string()), "dir/x.png".to_string());
+        assert_eq!(route(&config, None, "/minio/multi/b/dir/x.png"), expected);
+        assert_eq!(route(&config, Some("m.a.com"), "/b/dir/x.png"), expected);
+        assert!(matches!(resolve(&config, None, "/minio/multi/c/x.png"), Err(ProxyError::AccessDenied(_))));
+        assert!(matches!(resolve(&config, None, "/minio/multi/a"), Err(ProxyError::BadRequest(_))));
+    }
+}
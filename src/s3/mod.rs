@@ -0,0 +1,478 @@
+use std::collections::HashMap;
+
+use bytes::Bytes;
+use minio::s3::utils::to_iso8601utc;
+use tracing::Instrument;
+use warp::http::{HeaderMap, Method, StatusCode};
+use warp::{Filter, Rejection};
+
+use crate::backend::{self, ListQuery};
+use crate::config;
+use crate::config::power_config::BucketMode;
+use crate::config::s3_config::S3Credential;
+use crate::config::warp_config::WarpConfig;
+use crate::error::ProxyError;
+use crate::s3::sigv4::SignatureError;
+use crate::trace;
+use crate::utils::{decode, forwarded_headers, xml_escape, DEFAULT_MAX_PUT_SIZE};
+use crate::writes::{self, Change, WrittenKey};
+
+pub(crate) mod sigv4;
+
+const DEFAULT_REGION: &str = "us-east-1";
+
+// S3 格式的错误，响应体为 XML
+#[derive(Debug)]
+struct S3Error {
+    status: StatusCode,
+    code: &'static str,
+    message: String,
+}
+
+impl S3Error {
+    fn new(status: StatusCode, code: &'static str, message: impl Into<String>) -> Self {
+        S3Error { status, code, message: message.into() }
+    }
+
+    fn not_implemented(operation: &str) -> Self {
+        S3Error::new(StatusCode::NOT_IMPLEMENTED, "NotImplemented", format!("{} is not supported", operation))
+    }
+
+    fn reply(self, resource: &str, request_id: &str, head: bool) -> Box<dyn warp::Reply> {
+        if self.status.is_server_error() {
+            log::error!("S3 request failed: {} {}", self.code, self.message);
+        } else {
+            log::info!("S3 request rejected: {} {}", self.code, self.message);
+        }
+        // HEAD 请求不返回响应体
+        let body = if head {
+            String::new()
+        } else {
+            format!(
+                "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<Error><Code>{}</Code><Message>{}</Message><Resource>{}</Resource><RequestId>{}</RequestId></Error>",
+                self.code,
+                xml_escape(&self.message),
+                xml_escape(resource),
+                request_id,
+            )
+        };
+        let response = warp::http::Response::builder()
+            .status(self.status)
+            .header("Content-Type", "application/xml")
+            .body(body)
+            .unwrap();
+        Box::new(response)
+    }
+}
+
+impl From<ProxyError> for S3Error {
+    fn from(e: ProxyError) -> Self {
+        S3Error::new(e.status(), e.code(), e.to_string())
+    }
+}
+
+impl From<minio::s3::error::Error> for S3Error {
+    fn from(e: minio::s3::error::Error) -> Self {
+        ProxyError::from(e).into()
+    }
+}
+
+impl From<reqwest::Error> for S3Error {
+    fn from(e: reqwest::Error) -> Self {
+        ProxyError::from(e).into()
+    }
+}
+
+impl From<SignatureError> for S3Error {
+    fn from(e: SignatureError) -> Self {
+        match e {
+            SignatureError::Missing => S3Error::new(StatusCode::FORBIDDEN, "AccessDenied", "Anonymous access is not allowed"),
+            SignatureError::Malformed(message) => S3Error::new(StatusCode::BAD_REQUEST, "AuthorizationHeaderMalformed", message),
+            SignatureError::Expired => S3Error::new(
+                StatusCode::FORBIDDEN,
+                "RequestTimeTooSkewed",
+                "The difference between the request time and the server's time is too large",
+            ),
+            SignatureError::Mismatch => S3Error::new(
+                StatusCode::FORBIDDEN,
+                "SignatureDoesNotMatch",
+                "The request signature we calculated does not match the signature you provided",
+            ),
+        }
+    }
+}
+
+// 已通过签名校验的请求
+struct S3Request<'a> {
+    credential: &'a S3Credential,
+    method: Method,
+    headers: HeaderMap,
+    query: HashMap<String, String>,
+    bucket: String,
+    key: String,
+    body: Option<Bytes>,
+}
+
+// 在单独端口启动 S3 兼容接口，未配置 s3 时不启动
+pub async fn serve() {
+    let s3 = match config::current().s3.clone() {
+        Some(s3) => s3,
+        None => return,
+    };
+    let bind = s3.bind.as_deref().unwrap_or("127.0.0.1");
+    let addr: std::net::IpAddr = match bind.parse() {
+        Ok(addr) => addr,
+        Err(e) => {
+            log::error!("Invalid S3 bind address {}: {}", bind, e);
+            return;
+        }
+    };
+
+    // 只有 PutObject 读取请求体；超过上限或没有 Content-Length 时请求体为 None
+    let put_body = warp::put()
+        .and(warp::body::content_length_limit(s3.max_put_size.unwrap_or(DEFAULT_MAX_PUT_SIZE)))
+        .and(warp::body::bytes())
+        .map(Some);
+    let body = put_body.or(warp::any().map(|| None::<Bytes>)).unify();
+
+    let route = warp::path::full()
+        .and(warp::query::raw().or(warp::any().map(String::new)).unify())
+        .and(warp::method())
+        .and(warp::header::headers_cloned())
+        .and(body)
+        .and(trace::request_id())
+        .and_then(process);
+
+    log::info!("S3 API listening on {}:{}", addr, s3.port);
+    warp::serve(route).run((addr, s3.port)).await;
+}
+
+async fn process(
+    path: warp::path::FullPath,
+    raw_query: String,
+    method: Method,
+    headers: HeaderMap,
+    body: Option<Bytes>,
+    request_id: String,
+) -> Result<Box<dyn warp::Reply>, Rejection> {
+    let span = tracing::info_span!(
+        "s3_request",
+        request_id = %request_id,
+        method = %method,
+        path = %path.as_str(),
+    );
+    let head = method == Method::HEAD;
+    let reply = match handle(&config::current(), path.as_str(), &raw_query, method, headers, body).instrument(span).await {
+        Ok(reply) => reply,
+        Err(e) => e.reply(path.as_str(), &request_id, head),
+    };
+    let reply = warp::reply::with_header(reply, "x-amz-request-id", request_id.clone());
+    Ok(Box::new(warp::reply::with_header(reply, config::REQUEST_ID_HEADER, request_id)))
+}
+
+async fn handle(
+    config: &WarpConfig,
+    raw_path: &str,
+    raw_query: &str,
+    method: Method,
+    headers: HeaderMap,
+    body: Option<Bytes>,
+) -> Result<Box<dyn warp::Reply>, S3Error> {
+    let s3 = config
+        .s3
+        .as_ref()
+        .ok_or_else(|| S3Error::new(StatusCode::SERVICE_UNAVAILABLE, "ServiceUnavailable", "S3 API is disabled"))?;
+
+    let authorization = sigv4::parse_authorization(&headers)?;
+    let credential = s3
+        .credentials
+        .iter()
+        .find(|credential| credential.access_key == authorization.access_key)
+        .ok_or_else(|| S3Error::new(
+            StatusCode::FORBIDDEN,
+            "InvalidAccessKeyId",
+            "The access key Id you provided does not exist in our records",
+        ))?;
+    sigv4::verify(&authorization, credential.secret_key.expose(), &method, raw_path, raw_query, &headers)?;
+    verify_payload(&headers, body.as_ref())?;
+
+    let path = raw_path.trim_start_matches('/');
+    let (bucket, key) = path.split_once('/').unwrap_or((path, ""));
+    let request = S3Request {
+        credential,
+        method,
+        query: url::form_urlencoded::parse(raw_query.as_bytes()).into_owned().collect(),
+        bucket: decode(bucket),
+        key: decode(key),
+        headers,
+        body,
+    };
+    log::info!("S3 access: {} {}/{}", request.method, request.bucket, request.key);
+
+    let allowed = allowed_buckets(config, credential);
+    if request.bucket.is_empty() {
+        return match request.method {
+            Method::GET => Ok(list_buckets(&allowed)),
+            _ => Err(S3Error::not_implemented("This operation")),
+        };
+    }
+    if !allowed.contains(&request.bucket) {
+        return Err(S3Error::new(StatusCode::FORBIDDEN, "AccessDenied", format!("Access to bucket {} is denied", request.bucket)));
+    }
+    // 只读凭证只能读取，写入类请求(包括尚不支持的)一律拒绝
+    if credential.read_only && !matches!(request.method, Method::GET | Method::HEAD) {
+        return Err(S3Error::new(StatusCode::FORBIDDEN, "AccessDenied", "Credential is read-only"));
+    }
+
+    match (request.method.clone(), request.key.is_empty()) {
+        (Method::GET, true) if request.query.contains_key("location") => {
+            Ok(bucket_location(s3.region.as_deref().unwrap_or(DEFAULT_REGION)))
+        }
+        (Method::GET, true) => list_objects_v2(&request).await,
+        (Method::HEAD, true) => Ok(Box::new(StatusCode::OK)),
+        (Method::GET, false) => get_object(&request).await,
+        (Method::HEAD, false) => head_object(&request).await,
+        (Method::PUT, false) => put_object(&request).await,
+        (method, _) => Err(S3Error::not_implemented(&format!("{} on this resource", method))),
+    }
+}
+
+// 签名的请求体摘要需要与实际请求体一致
+fn verify_payload(headers: &HeaderMap, body: Option<&Bytes>) -> Result<(), S3Error> {
+    let hash = headers
+        .get("x-amz-content-sha256")
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or(sigv4::UNSIGNED_PAYLOAD);
+    if hash.starts_with("STREAMING-") {
+        return Err(S3Error::not_implemented("aws-chunked payload signing"));
+    }
+    if hash == sigv4::UNSIGNED_PAYLOAD {
+        return Ok(());
+    }
+    let actual = sigv4::sha256_hex(body.map(|b| b.as_ref()).unwrap_or_default());
+    if !actual.eq_ignore_ascii_case(hash) {
+        return Err(S3Error::new(
+            StatusCode::BAD_REQUEST,
+            "XAmzContentSHA256Mismatch",
+            "The provided x-amz-content-sha256 header does not match what was computed",
+        ));
+    }
+    Ok(())
+}
+
+// 凭证可以访问的桶：凭证指定的桶，否则为 power 的桶
+fn allowed_buckets(config: &WarpConfig, credential: &S3Credential) -> Vec<String> {
+    if let Some(bucket) = &credential.bucket {
+        return vec![bucket.clone()];
+    }
+    match config.power_config(&credential.power) {
+        Some(power) if power.bucket_mode == BucketMode::Path => power.buckets.clone().unwrap_or_default(),
+        _ => config
+            .bucket_name(credential.power.clone())
+            .or_else(|| config.default.bucket_name.clone())
+            .into_iter()
+            .collect(),
+    }
+}
+
+fn list_buckets(buckets: &[String]) -> Box<dyn warp::Reply> {
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<ListAllMyBucketsResult><Owner><ID>warp-minio</ID></Owner><Buckets>");
+    // 桶由配置声明，没有创建时间；S3 客户端要求 CreationDate 存在，固定返回纪元时间
+    for bucket in buckets {
+        xml.push_str(&format!(
+            "<Bucket><Name>{}</Name><CreationDate>1970-01-01T00:00:00.000Z</CreationDate></Bucket>",
+            xml_escape(bucket)
+        ));
+    }
+    xml.push_str("</Buckets></ListAllMyBucketsResult>");
+    xml_reply(xml)
+}
+
+fn bucket_location(region: &str) -> Box<dyn warp::Reply> {
+    xml_reply(format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<LocationConstraint xmlns=\"http://s3.amazonaws.com/doc/2006-03-01/\">{}</LocationConstraint>",
+        xml_escape(region)
+    ))
+}
+
+async fn list_objects_v2(request: &S3Request<'_>) -> Result<Box<dyn warp::Reply>, S3Error> {
+    if request.query.get("list-type").map(String::as_str) != Some("2") {
+        return Err(S3Error::not_implemented("ListObjects (v1)"));
+    }
+    let param = |name: &str| request.query.get(name).map(String::as_str).filter(|v| !v.is_empty());
+
+    let query = ListQuery {
+        prefix: param("prefix").unwrap_or(""),
+        delimiter: param("delimiter"),
+        max_keys: param("max-keys").and_then(|v| v.parse::<u16>().ok()).map(|n| n.min(1000)),
+        continuation_token: param("continuation-token").map(str::to_string),
+        start_after: param("start-after").map(str::to_string),
+    };
+    let max_keys = query.max_keys.unwrap_or(1000);
+    let page = backend::for_power(&request.credential.power)?.list_page(&request.bucket, query).await?;
+
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<ListBucketResult xmlns=\"http://s3.amazonaws.com/doc/2006-03-01/\">");
+    let mut tag = |name: &str, value: &str| xml.push_str(&format!("<{0}>{1}</{0}>", name, xml_escape(value)));
+    tag("Name", &request.bucket);
+    tag("Prefix", param("prefix").unwrap_or(""));
+    if let Some(delimiter) = param("delimiter") {
+        tag("Delimiter", delimiter);
+    }
+    tag("MaxKeys", &max_keys.to_string());
+    tag("KeyCount", &page.entries.len().to_string());
+    tag("IsTruncated", &page.is_truncated.to_string());
+    if let Some(token) = param("continuation-token") {
+        tag("ContinuationToken", token);
+    }
+    if let Some(token) = &page.next_continuation_token {
+        tag("NextContinuationToken", token);
+    }
+    if let Some(start_after) = param("start-after") {
+        tag("StartAfter", start_after);
+    }
+    for entry in page.entries.iter().filter(|entry| !entry.is_prefix) {
+        xml.push_str("<Contents>");
+        xml.push_str(&format!("<Key>{}</Key>", xml_escape(&entry.name)));
+        if let Some(last_modified) = entry.last_modified {
+            xml.push_str(&format!("<LastModified>{}</LastModified>", to_iso8601utc(last_modified)));
+        }
+        if let Some(etag) = &entry.etag {
+            xml.push_str(&format!("<ETag>&quot;{}&quot;</ETag>", xml_escape(etag.trim_matches('"'))));
+        }
+        xml.push_str(&format!("<Size>{}</Size>", entry.size));
+        xml.push_str("<StorageClass>STANDARD</StorageClass>");
+        xml.push_str("</Contents>");
+    }
+    for entry in page.entries.iter().filter(|entry| entry.is_prefix) {
+        xml.push_str(&format!("<CommonPrefixes><Prefix>{}</Prefix></CommonPrefixes>", xml_escape(&entry.name)));
+    }
+    xml.push_str("</ListBucketResult>");
+    Ok(xml_reply(xml))
+}
+
+async fn get_object(request: &S3Request<'_>) -> Result<Box<dyn warp::Reply>, S3Error> {
+    let object = backend::for_power(&request.credential.power)?
+        .get(&request.bucket, &request.key, &request.headers)
+        .await?;
+
+    let mut reply = warp::http::Response::new(object.body);
+    *reply.status_mut() = object.status;
+    *reply.headers_mut() = forwarded_headers(&object.headers);
+    Ok(Box::new(reply))
+}
+
+async fn head_object(request: &S3Request<'_>) -> Result<Box<dyn warp::Reply>, S3Error> {
+    let meta = backend::for_power(&request.credential.power)?
+        .stat(&request.bucket, &request.key)
+        .await?;
+    let mut reply = warp::http::Response::new(warp::hyper::Body::empty());
+    *reply.headers_mut() = meta.headers();
+    Ok(Box::new(reply))
+}
+
+async fn put_object(request: &S3Request<'_>) -> Result<Box<dyn warp::Reply>, S3Error> {
+    if request.headers.contains_key("x-amz-copy-source") {
+        return Err(S3Error::not_implemented("CopyObject"));
+    }
+    let body = request.body.as_ref().ok_or_else(|| S3Error::new(
+        StatusCode::PAYLOAD_TOO_LARGE,
+        "EntityTooLarge",
+        "Request body is missing Content-Length or exceeds max-put-size",
+    ))?;
+
+    let put_headers = backend::stored_headers(&request.headers);
+
+    let etag = backend::for_power(&request.credential.power)?
+        .put(&request.bucket, &request.key, body.clone(), put_headers)
+        .await?;
+    writes::written(&request.credential.power, &request.bucket, WrittenKey::Stored(&request.key), Change::Put).await;
+
+    let reply = warp::reply::with_header(StatusCode::OK, "ETag", format!("\"{}\"", etag));
+    Ok(Box::new(reply))
+}
+
+fn xml_reply(xml: String) -> Box<dyn warp::Reply> {
+    Box::new(warp::reply::with_header(xml, "Content-Type", "application/xml"))
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+
+    use super::*;
+    use crate::s3::sigv4::tests::signed;
+
+    const CONFIG: &str = "
+power:
+  atom: {bucket-name: atom}
+  multi: {bucket-mode: path, buckets: [bkt-a, bkt-b]}
+s3:
+  port: 9000
+  credentials:
+    - {access-key: scoped, secret-key: scoped-secret, power: multi, bucket: bkt-a}
+    - {access-key: power, secret-key: power-secret, power: multi}
+    - {access-key: reader, secret-key: reader-secret, power: atom, read-only: true}
+";
+
+    async fn call(method: Method, path: &str, credential: (&str, &str), content_sha256: &str, body: Option<&str>) -> Result<(), S3Error> {
+        let config: WarpConfig = serde_yaml::from_str(CONFIG).unwrap();
+        let (query, headers) = signed(&method, path, &[], credential, content_sha256, Some("127.0.0.1:9000"), Utc::now());
+        let body = body.map(|body| Bytes::from(body.to_string()));
+        handle(&config, path, &query, method, headers, body).await.map(|_| ())
+    }
+
+    fn rejected(result: Result<(), S3Error>) -> (StatusCode, &'static str) {
+        let error = result.expect_err("request should be rejected");
+        (error.status, error.code)
+    }
+
+    #[tokio::test]
+    async fn credential_is_limited_to_its_bucket() {
+        let scoped = ("scoped", "scoped-secret");
+        let denied = (StatusCode::FORBIDDEN, "AccessDenied");
+        assert_eq!(rejected(call(Method::GET, "/bkt-b/a.txt", scoped, sigv4::UNSIGNED_PAYLOAD, None).await), denied);
+        assert_eq!(rejected(call(Method::GET, "/atom/a.txt", scoped, sigv4::UNSIGNED_PAYLOAD, None).await), denied);
+        // 未指定桶的凭证只能访问自己 power 的桶
+        let power = ("power", "power-secret");
+        assert_eq!(rejected(call(Method::HEAD, "/atom", power, sigv4::UNSIGNED_PAYLOAD, None).await), denied);
+        assert!(call(Method::HEAD, "/bkt-b", power, sigv4::UNSIGNED_PAYLOAD, None).await.is_ok());
+        assert!(call(Method::HEAD, "/bkt-a", scoped, sigv4::UNSIGNED_PAYLOAD, None).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn read_only_credential_cannot_write() {
+        let reader = ("reader", "reader-secret");
+        let denied = (StatusCode::FORBIDDEN, "AccessDenied");
+        let hash = sigv4::sha256_hex(b"data");
+        assert_eq!(rejected(call(Method::PUT, "/atom/a.txt", reader, &hash, Some("data")).await), denied);
+        assert_eq!(rejected(call(Method::DELETE, "/atom/a.txt", reader, sigv4::UNSIGNED_PAYLOAD, None).await), denied);
+        assert!(call(Method::HEAD, "/atom", reader, sigv4::UNSIGNED_PAYLOAD, None).await.is_ok());
+        // 可写凭证的 DELETE 尚不支持
+        let power = ("power", "power-secret");
+        let result = call(Method::DELETE, "/bkt-a/a.txt", power, sigv4::UNSIGNED_PAYLOAD, None).await;
+        assert_eq!(rejected(result), (StatusCode::NOT_IMPLEMENTED, "NotImplemented"));
+    }
+
+    #[tokio::test]
+    async fn payload_must_match_signed_hash() {
+        let power = ("power", "power-secret");
+        let hash = sigv4::sha256_hex(b"data");
+        let result = call(Method::PUT, "/bkt-a/a.txt", power, &hash, Some("tampered")).await;
+        assert_eq!(rejected(result), (StatusCode::BAD_REQUEST, "XAmzContentSHA256Mismatch"));
+    }
+
+    #[tokio::test]
+    async fn streaming_payload_is_rejected() {
+        let power = ("power", "power-secret");
+        let result = call(Method::PUT, "/bkt-a/a.txt", power, "STREAMING-AWS4-HMAC-SHA256-PAYLOAD", Some("data")).await;
+        assert_eq!(rejected(result), (StatusCode::NOT_IMPLEMENTED, "NotImplemented"));
+    }
+
+    #[tokio::test]
+    async fn bad_signature_is_rejected() {
+        let result = call(Method::GET, "/bkt-a/a.txt", ("scoped", "wrong-secret"), sigv4::UNSIGNED_PAYLOAD, None).await;
+        assert_eq!(rejected(result), (StatusCode::FORBIDDEN, "SignatureDoesNotMatch"));
+        let result = call(Method::GET, "/bkt-a/a.txt", ("unknown", "scoped-secret"), sigv4::UNSIGNED_PAYLOAD, None).await;
+        assert_eq!(rejected(result), (StatusCode::FORBIDDEN, "InvalidAccessKeyId"));
+    }
+}
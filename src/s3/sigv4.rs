@@ -0,0 +1,422 @@
+use chrono::{DateTime, NaiveDateTime, Utc};
+use hmac::{Hmac, Mac};
+use percent_encoding::{percent_decode_str, utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+use sha2::{Digest, Sha256};
+use warp::http::{HeaderMap, Method};
+
+use crate::utils::constant_time_eq;
+
+const ALGORITHM: &str = "AWS4-HMAC-SHA256";
+
+// 请求时间与服务器时间允许的最大偏差(秒)
+const MAX_CLOCK_SKEW_SECS: i64 = 15 * 60;
+
+pub const UNSIGNED_PAYLOAD: &str = "UNSIGNED-PAYLOAD";
+
+// SigV4 查询参数编码，只保留 RFC 3986 非保留字符
+const QUERY_ENCODE_SET: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'_')
+    .remove(b'.')
+    .remove(b'~');
+
+// Authorization 请求头中解析出的签名信息
+#[derive(Debug)]
+pub struct Authorization {
+    pub(crate) access_key: String,
+    date: String,
+    region: String,
+    service: String,
+    signed_headers: Vec<String>,
+    signature: String,
+}
+
+// 验证失败的原因，对应 S3 错误码
+#[derive(Debug)]
+pub enum SignatureError {
+    Missing,
+    Malformed(String),
+    Expired,
+    Mismatch,
+}
+
+pub fn parse_authorization(headers: &HeaderMap) -> Result<Authorization, SignatureError> {
+    let value = headers
+        .get("authorization")
+        .and_then(|v| v.to_str().ok())
+        .ok_or(SignatureError::Missing)?;
+    let rest = value
+        .strip_prefix(ALGORITHM)
+        .ok_or_else(|| SignatureError::Malformed(format!("Only {} is supported", ALGORITHM)))?;
+
+    let (mut credential, mut signed_headers, mut signature) = (None, None, None);
+    for part in rest.split(',') {
+        match part.trim().split_once('=') {
+            Some(("Credential", v)) => credential = Some(v),
+            Some(("SignedHeaders", v)) => signed_headers = Some(v),
+            Some(("Signature", v)) => signature = Some(v),
+            _ => {}
+        }
+    }
+    let malformed = || SignatureError::Malformed("Authorization header is malformed".to_string());
+    let scope: Vec<&str> = credential.ok_or_else(malformed)?.split('/').collect();
+    let [access_key, date, region, service, terminal] = scope[..] else {
+        return Err(malformed());
+    };
+    if terminal != "aws4_request" {
+        return Err(malformed());
+    }
+
+    Ok(Authorization {
+        access_key: access_key.to_string(),
+        date: date.to_string(),
+        region: region.to_string(),
+        service: service.to_string(),
+        signed_headers: signed_headers.ok_or_else(malformed)?.split(';').map(str::to_string).collect(),
+        signature: signature.ok_or_else(malformed)?.to_string(),
+    })
+}
+
+// 按 SigV4 重新计算签名并与请求中的签名比较
+pub fn verify(
+    auth: &Authorization,
+    secret_key: &str,
+    method: &Method,
+    raw_path: &str,
+    raw_query: &str,
+    headers: &HeaderMap,
+) -> Result<(), SignatureError> {
+    verify_at(auth, secret_key, method, raw_path, raw_query, headers, Utc::now())
+}
+
+fn verify_at(
+    auth: &Authorization,
+    secret_key: &str,
+    method: &Method,
+    raw_path: &str,
+    raw_query: &str,
+    headers: &HeaderMap,
+    now: DateTime<Utc>,
+) -> Result<(), SignatureError> {
+    let amz_date = headers
+        .get("x-amz-date")
+        .and_then(|v| v.to_str().ok())
+        .ok_or_else(|| SignatureError::Malformed("Missing x-amz-date header".to_string()))?;
+    let time = NaiveDateTime::parse_from_str(amz_date, "%Y%m%dT%H%M%SZ")
+        .map(|t| DateTime::<Utc>::from_naive_utc_and_offset(t, Utc))
+        .map_err(|_| SignatureError::Malformed("Invalid x-amz-date header".to_string()))?;
+    if (now - time).num_seconds().abs() > MAX_CLOCK_SKEW_SECS {
+        return Err(SignatureError::Expired);
+    }
+    if !amz_date.starts_with(&auth.date) || auth.service != "s3" {
+        return Err(SignatureError::Malformed("Credential scope does not match the request".to_string()));
+    }
+    if !auth.signed_headers.iter().any(|h| h == "host") {
+        return Err(SignatureError::Malformed("The host header must be signed".to_string()));
+    }
+
+    let payload_hash = headers
+        .get("x-amz-content-sha256")
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or(UNSIGNED_PAYLOAD);
+    let canonical_request = canonical_request(auth, method, raw_path, raw_query, headers, payload_hash);
+    let expected = signature(auth, secret_key, amz_date, &canonical_request);
+
+    if constant_time_eq(expected.as_bytes(), auth.signature.as_bytes()) {
+        Ok(())
+    } else {
+        Err(SignatureError::Mismatch)
+    }
+}
+
+// S3 的路径不再次编码，按请求中的原始路径签名
+fn canonical_request(
+    auth: &Authorization,
+    method: &Method,
+    raw_path: &str,
+    raw_query: &str,
+    headers: &HeaderMap,
+    payload_hash: &str,
+) -> String {
+    format!(
+        "{}\n{}\n{}\n{}\n{}\n{}",
+        method.as_str(),
+        raw_path,
+        canonical_query(raw_query),
+        canonical_headers(headers, &auth.signed_headers),
+        auth.signed_headers.join(";"),
+        payload_hash,
+    )
+}
+
+fn signature(auth: &Authorization, secret_key: &str, amz_date: &str, canonical_request: &str) -> String {
+    let scope = format!("{}/{}/{}/aws4_request", auth.date, auth.region, auth.service);
+    let string_to_sign = format!(
+        "{}\n{}\n{}\n{}",
+        ALGORITHM,
+        amz_date,
+        scope,
+        sha256_hex(canonical_request.as_bytes()),
+    );
+
+    let mut key = hmac(format!("AWS4{}", secret_key).as_bytes(), auth.date.as_bytes());
+    for part in [auth.region.as_str(), auth.service.as_str(), "aws4_request"] {
+        key = hmac(&key, part.as_bytes());
+    }
+    hex::encode(hmac(&key, string_to_sign.as_bytes()))
+}
+
+pub fn sha256_hex(data: &[u8]) -> String {
+    hex::encode(Sha256::digest(data))
+}
+
+fn hmac(key: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(data);
+    mac.finalize().into_bytes().to_vec()
+}
+
+// 查询参数解码后重新编码，按参数名、参数值排序
+fn canonical_query(raw_query: &str) -> String {
+    let mut params: Vec<(String, String)> = raw_query
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            (encode_query(key), encode_query(value))
+        })
+        .collect();
+    params.sort();
+    params
+        .iter()
+        .map(|(key, value)| format!("{}={}", key, value))
+        .collect::<Vec<_>>()
+        .join("&")
+}
+
+fn encode_query(value: &str) -> String {
+    let decoded = percent_decode_str(value).decode_utf8_lossy();
+    utf8_percent_encode(&decoded, QUERY_ENCODE_SET).to_string()
+}
+
+// 签名的请求头：名称小写，多个值用逗号连接，连续空白压缩为一个空格
+fn canonical_headers(headers: &HeaderMap, signed_headers: &[String]) -> String {
+    let mut canonical = String::new();
+    for name in signed_headers {
+        let values: Vec<String> = headers
+            .get_all(name.as_str())
+            .iter()
+            .map(|v| {
+                String::from_utf8_lossy(v.as_bytes())
+                    .split_whitespace()
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            })
+            .collect();
+        canonical.push_str(&format!("{}:{}\n", name, values.join(",")));
+    }
+    canonical
+}
+
+#[cfg(test)]
+pub(super) mod tests {
+    use chrono::Duration;
+    use minio::s3::signer::sign_v4_s3;
+    use minio::s3::utils::{to_amz_date, Multimap};
+
+    use super::*;
+
+    // AWS SigV4 测试套件的公共参数
+    const SUITE_SECRET: &str = "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY";
+    const SUITE_DATE: &str = "20150830T123600Z";
+
+    fn headers(pairs: &[(&str, &str)]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for (name, value) in pairs {
+            headers.append(
+                warp::http::HeaderName::from_bytes(name.as_bytes()).unwrap(),
+                value.parse().unwrap(),
+            );
+        }
+        headers
+    }
+
+    fn suite_auth(signed_headers: &str) -> Authorization {
+        let value = format!(
+            "AWS4-HMAC-SHA256 Credential=AKIDEXAMPLE/20150830/us-east-1/service/aws4_request, SignedHeaders={}, Signature=x",
+            signed_headers
+        );
+        parse_authorization(&headers(&[("authorization", &value)])).unwrap()
+    }
+
+    fn suite_signature(path: &str, query: &str, extra: &[(&str, &str)], signed_headers: &str) -> (String, String) {
+        let mut pairs = vec![("host", "example.amazonaws.com"), ("x-amz-date", SUITE_DATE)];
+        pairs.extend_from_slice(extra);
+        let auth = suite_auth(signed_headers);
+        let canonical = canonical_request(&auth, &Method::GET, path, query, &headers(&pairs), &sha256_hex(b""));
+        let signature = signature(&auth, SUITE_SECRET, SUITE_DATE, &canonical);
+        (canonical, signature)
+    }
+
+    #[test]
+    fn suite_get_vanilla() {
+        let (canonical, signature) = suite_signature("/", "", &[], "host;x-amz-date");
+        assert_eq!(canonical, "GET\n/\n\nhost:example.amazonaws.com\nx-amz-date:20150830T123600Z\n\nhost;x-amz-date\ne3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855");
+        assert_eq!(signature, "5fa00fa31553b73ebf1942676e86291e8372ff2a2260956d9b8aae1d763fbf31");
+    }
+
+    #[test]
+    fn suite_query_canonicalization() {
+        let cases = [
+            ("Param2=value2&Param1=value1", "Param1=value1&Param2=value2", "b97d918cfa904a5beff61c982a1b6f458b799221646efd99d3219ec94cdf2500"),
+            ("Param1=value2&Param1=value1", "Param1=value1&Param1=value2", "5772eed61e12b33fae39ee5e7012498b51d56abc0abb7c60486157bd471c4694"),
+            ("Param1=value1", "Param1=value1", "a67d582fa61cc504c4bae71f336f98b97f1ea3c7a6bfe1b6e45aec72011b9aeb"),
+            ("%E1%88%B4=bar", "%E1%88%B4=bar", "2cdec8eed098649ff3a119c94853b13c643bcf08f8b0a1d91e12c9027818dd04"),
+            (
+                "-._~0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz=-._~0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz",
+                "-._~0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz=-._~0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz",
+                "9c3e54bfcdf0b19771a7f523ee5669cdf59bc7cc0884027167c21bb143a40197",
+            ),
+        ];
+        for (query, canonical_query, expected) in cases {
+            let (canonical, signature) = suite_signature("/", query, &[], "host;x-amz-date");
+            assert_eq!(canonical.lines().nth(2), Some(canonical_query), "{}", query);
+            assert_eq!(signature, expected, "{}", query);
+        }
+    }
+
+    #[test]
+    fn suite_header_canonicalization() {
+        let (canonical, signature) = suite_signature(
+            "/",
+            "",
+            &[("My-Header1", "value2"), ("My-Header1", "value2"), ("My-Header1", "value1")],
+            "host;my-header1;x-amz-date",
+        );
+        assert!(canonical.contains("\nmy-header1:value2,value2,value1\n"), "{}", canonical);
+        assert_eq!(signature, "c9d5ea9f3f72853aea855b47ea873832890dbdd183b4468f858259531a5138ea");
+
+        let (canonical, signature) = suite_signature(
+            "/",
+            "",
+            &[("My-Header1", " value1"), ("My-Header2", " \"a   b   c\"")],
+            "host;my-header1;my-header2;x-amz-date",
+        );
+        assert!(canonical.contains("\nmy-header1:value1\nmy-header2:\"a b c\"\n"), "{}", canonical);
+        assert_eq!(signature, "acc3ed3afb60bb290fc8d2dd0098b9911fcaa05412b367055dee359757a9c736");
+    }
+
+    #[test]
+    fn suite_encoded_paths() {
+        let (_, signature) = suite_signature("/%E1%88%B4", "", &[], "host;x-amz-date");
+        assert_eq!(signature, "8318018e0b0f223aa2bbf98705b62bb787dc9c0e678f255a891fd03141be5d85");
+        let (_, signature) = suite_signature("/example%20space/", "", &[], "host;x-amz-date");
+        assert_eq!(signature, "652487583200325589f1fba4c7e578f72c47cb61beeca81406b39ddec1366741");
+    }
+
+    const ACCESS_KEY: &str = "AKTEST";
+    const SECRET_KEY: &str = "sekrit123";
+
+    // 用 minio 客户端的签名实现生成请求头，与本模块的校验相互独立；host 为 None 时不签名 Host
+    pub(in crate::s3) fn signed(
+        method: &Method,
+        path: &str,
+        query: &[(&str, &str)],
+        (access_key, secret_key): (&str, &str),
+        content_sha256: &str,
+        host: Option<&str>,
+        date: DateTime<Utc>,
+    ) -> (String, HeaderMap) {
+        let mut signing = Multimap::new();
+        if let Some(host) = host {
+            signing.insert("Host".to_string(), host.to_string());
+        }
+        signing.insert("x-amz-date".to_string(), to_amz_date(date));
+        signing.insert("x-amz-content-sha256".to_string(), content_sha256.to_string());
+        let mut params = Multimap::new();
+        for (key, value) in query {
+            params.insert(key.to_string(), value.to_string());
+        }
+        sign_v4_s3(method, path, "us-east-1", &mut signing, &params, access_key, secret_key, content_sha256, date);
+
+        let mut headers = HeaderMap::new();
+        for (name, values) in signing.iter_all() {
+            for value in values {
+                headers.append(warp::http::HeaderName::from_bytes(name.as_bytes()).unwrap(), value.parse().unwrap());
+            }
+        }
+        headers.entry("host").or_insert_with(|| "127.0.0.1:9000".parse().unwrap());
+        let raw_query = query.iter().map(|(key, value)| format!("{}={}", key, value)).collect::<Vec<_>>().join("&");
+        (raw_query, headers)
+    }
+
+    fn check(path: &str, raw_query: &str, headers: &HeaderMap, secret_key: &str, now: DateTime<Utc>) -> Result<(), SignatureError> {
+        let auth = parse_authorization(headers)?;
+        verify_at(&auth, secret_key, &Method::GET, path, raw_query, headers, now)
+    }
+
+    fn request(query: &[(&str, &str)], date: DateTime<Utc>) -> (String, HeaderMap) {
+        signed(&Method::GET, "/bkt/a%20b.txt", query, (ACCESS_KEY, SECRET_KEY), UNSIGNED_PAYLOAD, Some("127.0.0.1:9000"), date)
+    }
+
+    #[test]
+    fn minio_signature_is_accepted() {
+        let now = Utc::now();
+        let (query, headers) = request(&[("versionId", "1"), ("acl", "")], now);
+        assert!(check("/bkt/a%20b.txt", &query, &headers, SECRET_KEY, now).is_ok());
+    }
+
+    #[test]
+    fn tampered_signature_is_rejected() {
+        let now = Utc::now();
+        let (query, mut headers) = request(&[], now);
+        let authorization = headers["authorization"].to_str().unwrap().to_string();
+        let last = authorization.chars().last().unwrap();
+        let tampered = format!("{}{}", &authorization[..authorization.len() - 1], if last == '0' { '1' } else { '0' });
+        headers.insert("authorization", tampered.parse().unwrap());
+        assert!(matches!(check("/bkt/a%20b.txt", &query, &headers, SECRET_KEY, now), Err(SignatureError::Mismatch)));
+        // 签名后修改路径、参数同样无法通过
+        let (query, headers) = request(&[], now);
+        assert!(matches!(check("/bkt/other.txt", &query, &headers, SECRET_KEY, now), Err(SignatureError::Mismatch)));
+        assert!(matches!(check("/bkt/a%20b.txt", "x=1", &headers, SECRET_KEY, now), Err(SignatureError::Mismatch)));
+    }
+
+    #[test]
+    fn wrong_secret_is_rejected() {
+        let now = Utc::now();
+        let (query, headers) = request(&[], now);
+        assert!(matches!(check("/bkt/a%20b.txt", &query, &headers, "other-secret", now), Err(SignatureError::Mismatch)));
+    }
+
+    #[test]
+    fn clock_skew_is_limited_to_fifteen_minutes() {
+        let signed_at = Utc::now();
+        let (query, headers) = request(&[], signed_at);
+        for (offset, accepted) in [(-14, true), (14, true), (-16, false), (16, false)] {
+            let result = check("/bkt/a%20b.txt", &query, &headers, SECRET_KEY, signed_at + Duration::minutes(offset));
+            match accepted {
+                true => assert!(result.is_ok(), "{} minutes", offset),
+                false => assert!(matches!(result, Err(SignatureError::Expired)), "{} minutes", offset),
+            }
+        }
+    }
+
+    #[test]
+    fn host_must_be_signed() {
+        let now = Utc::now();
+        let (query, headers) = signed(&Method::GET, "/bkt/a.txt", &[], (ACCESS_KEY, SECRET_KEY), UNSIGNED_PAYLOAD, None, now);
+        assert!(!headers["authorization"].to_str().unwrap().contains("host"));
+        assert!(matches!(check("/bkt/a.txt", &query, &headers, SECRET_KEY, now), Err(SignatureError::Malformed(_))));
+    }
+
+    #[test]
+    fn payload_hash_is_signed() {
+        let now = Utc::now();
+        let hash = sha256_hex(b"body");
+        let (query, mut headers) =
+            signed(&Method::GET, "/bkt/a.txt", &[], (ACCESS_KEY, SECRET_KEY), &hash, Some("127.0.0.1:9000"), now);
+        assert!(check("/bkt/a.txt", &query, &headers, SECRET_KEY, now).is_ok());
+        headers.insert("x-amz-content-sha256", sha256_hex(b"other").parse().unwrap());
+        assert!(matches!(check("/bkt/a.txt", &query, &headers, SECRET_KEY, now), Err(SignatureError::Mismatch)));
+    }
+}
@@ -0,0 +1,132 @@
+use opentelemetry::KeyValue;
+use opentelemetry_otlp::WithExportConfig;
+use opentelemetry_sdk::{runtime, Resource};
+use opentelemetry_sdk::trace::{self, Sampler, Tracer};
+use rand::Rng;
+use tracing_opentelemetry::OpenTelemetryLayer;
+use tracing_subscriber::{EnvFilter, Registry, reload};
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use warp::{Filter, Rejection};
+
+use crate::config;
+
+// 请求ID最大长度，超过则重新生成，避免被注入超长请求头
+const MAX_REQUEST_ID_LEN: usize = 128;
+
+// 初始化日志与链路追踪，log 宏的输出会一并转发到 tracing，日志输出到 stderr
+// 命令行子命令只输出本地日志，默认只显示警告，避免干扰命令输出
+pub fn init_logging() {
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("warn"));
+    tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_writer(std::io::stderr)
+        .init();
+}
+
+pub fn init_tracing() {
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"));
+    // 先安装本地日志，保证读取配置文件时的日志不会丢失，再按配置挂载 OTLP 导出
+    let (otel_layer, otel_handle) = reload::Layer::new(None::<OpenTelemetryLayer<Registry, Tracer>>);
+
+    Registry::default()
+        .with(otel_layer)
+        .with(filter)
+        .with(tracing_subscriber::fmt::layer().with_writer(std::io::stderr))
+        .init();
+
+    let tracing_config = config::current().tracing.clone().unwrap_or_default();
+    let endpoint = match tracing_config.otlp_endpoint {
+        None => return,
+        Some(endpoint) => endpoint,
+    };
+    let service_name = tracing_config
+        .service_name
+        .unwrap_or_else(|| config::SERVICE_NAME.to_string());
+    let sampler = Sampler::TraceIdRatioBased(tracing_config.sample_ratio.unwrap_or(1.0));
+
+    let tracer = opentelemetry_otlp::new_pipeline()
+        .tracing()
+        .with_exporter(
+            opentelemetry_otlp::new_exporter()
+                .http()
+                .with_endpoint(&endpoint),
+        )
+        .with_trace_config(
+            trace::config()
+                .with_sampler(Sampler::ParentBased(Box::new(sampler)))
+                .with_resource(Resource::new(vec![KeyValue::new("service.name", service_name)])),
+        )
+        .install_batch(runtime::Tokio);
+
+    match tracer {
+        Ok(tracer) => {
+            let layer = tracing_opentelemetry::layer().with_tracer(tracer);
+            if let Err(e) = otel_handle.reload(Some(layer)) {
+                log::error!("Failed to install OTLP layer: {}", e);
+                return;
+            }
+            log::info!("OTLP tracing exporter enabled: {}", endpoint);
+        }
+        Err(e) => log::error!("Failed to initialize OTLP exporter: {}", e),
+    }
+}
+
+// 等待 Ctrl-C 或 SIGTERM，收到后停止接收新请求
+pub async fn shutdown_signal() {
+    let ctrl_c = async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            log::error!("Failed to listen for Ctrl-C: {}", e);
+            std::future::pending::<()>().await;
+        }
+    };
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut signal) => {
+                signal.recv().await;
+            }
+            Err(e) => {
+                log::error!("Failed to listen for SIGTERM: {}", e);
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {}
+        _ = terminate => {}
+    }
+    log::info!("Shutdown signal received");
+}
+
+// 退出前导出 OTLP 批量导出器中缓存的 span；关闭过程会阻塞等待导出完成，放到阻塞线程执行
+// 未启用 OTLP 时全局是空的 provider，关闭没有副作用
+pub async fn shutdown_tracing() {
+    if let Err(e) = tokio::task::spawn_blocking(opentelemetry::global::shutdown_tracer_provider).await {
+        log::error!("Failed to shut down OTLP exporter: {}", e);
+    }
+}
+
+// 读取请求中的 X-Request-Id，不存在或不合法时生成新的ID
+pub fn request_id() -> impl Filter<Extract=(String, ), Error=Rejection> + Clone {
+    warp::header::optional::<String>(config::REQUEST_ID_HEADER)
+        .map(|header: Option<String>| {
+            header
+                .filter(|id| is_valid_request_id(id))
+                .unwrap_or_else(generate_request_id)
+        })
+}
+
+fn is_valid_request_id(id: &str) -> bool {
+    !id.is_empty()
+        && id.len() <= MAX_REQUEST_ID_LEN
+        && id.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'_' || b == b'.')
+}
+
+fn generate_request_id() -> String {
+    let bytes: [u8; 16] = rand::thread_rng().gen();
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
@@ -0,0 +1,47 @@
+use mime_guess::from_path;
+use warp::http::{HeaderMap, HeaderValue};
+
+use crate::auth;
+use crate::backend;
+use crate::config;
+use crate::error::ProxyError;
+use crate::minio::minio_parser;
+use crate::rewrite;
+use crate::routing::Route;
+use crate::utils::{read_body, BodyStream, DEFAULT_MAX_PUT_SIZE};
+use crate::writes::{self, Change, WrittenKey};
+
+// 通过代理上传对象，同步写入当前 power，再按 replication 配置异步复制
+pub async fn put_object(route: &Route, headers: &HeaderMap, body: BodyStream) -> Result<Box<dyn warp::Reply>, ProxyError> {
+    let config_key = route.config_key.as_str();
+    let object_key = route.object_key.as_str();
+    if !auth::check(headers.clone(), config_key).await {
+        return Err(ProxyError::Unauthorized);
+    }
+    let upload = config::current()
+        .power_config(config_key)
+        .and_then(|power| power.upload.clone())
+        .ok_or_else(|| ProxyError::MethodNotAllowed(format!("Uploads are not enabled for {}", config_key)))?;
+    if object_key.is_empty() || object_key.ends_with('/') {
+        return Err(ProxyError::BadRequest("PUT requires an object key".to_string()));
+    }
+
+    let body = read_body(body, headers, upload.max_put_size.unwrap_or(DEFAULT_MAX_PUT_SIZE)).await?;
+    let mut put_headers = backend::stored_headers(headers);
+    if !put_headers.contains_key("content-type") {
+        put_headers.insert("content-type".to_string(), from_path(object_key).first_or_octet_stream().to_string());
+    }
+
+    let stored_key = rewrite::rewrite_object_key(config_key, object_key);
+    let bucket = minio_parser::bucket_for(config_key, route.bucket.as_deref()).await;
+    let etag = backend::for_power(config_key)?.put(&bucket, &stored_key, body, put_headers).await?;
+    log::info!("Uploaded {}/{} to {}", bucket, stored_key, config_key);
+
+    writes::written(config_key, &bucket, WrittenKey::Public(object_key), Change::Put).await;
+
+    let mut response = warp::http::Response::new(warp::hyper::Body::empty());
+    if let Ok(value) = HeaderValue::from_str(&format!("\"{}\"", etag.trim_matches('"'))) {
+        response.headers_mut().insert("ETag", value);
+    }
+    Ok(Box::new(response))
+}
@@ -0,0 +1,123 @@
+use bytes::{Buf, Bytes, BytesMut};
+use futures_util::stream::BoxStream;
+use futures_util::{Stream, StreamExt, TryStreamExt};
+use percent_encoding::percent_decode_str;
+use warp::http::HeaderMap;
+
+use crate::error::ProxyError;
+
+// 上传、WebDAV PUT、S3 PutObject 默认的请求体大小上限
+pub const DEFAULT_MAX_PUT_SIZE: u64 = 64 * 1024 * 1024;
+
+// WebDAV GET 和 S3 GetObject 透传给客户端的对象响应头，另外保留 x-amz-meta-*
+const FORWARD_HEADERS: [&str; 10] = [
+    "content-type",
+    "content-length",
+    "content-range",
+    "content-encoding",
+    "content-disposition",
+    "cache-control",
+    "expires",
+    "etag",
+    "last-modified",
+    "accept-ranges",
+];
+
+// 请求体流，由 warp::body::stream() 经 boxed_body 转换得到
+pub type BodyStream = BoxStream<'static, Result<Bytes, warp::Error>>;
+
+// 通配符匹配，`*` 匹配任意长度字符，`?` 匹配单个字符
+pub fn wildcard_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    let (mut p, mut t) = (0, 0);
+    let mut star: Option<(usize, usize)> = None;
+
+    while t < text.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == text[t]) {
+            p += 1;
+            t += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            star = Some((p, t));
+            p += 1;
+        } else if let Some((star_p, star_t)) = star {
+            // 回溯到上一个 `*`，让它多匹配一个字符
+            p = star_p + 1;
+            t = star_t + 1;
+            star = Some((star_p, star_t + 1));
+        } else {
+            return false;
+        }
+    }
+
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+// 不区分大小写的通配符匹配，用于响应头名称、Content-Type、域名
+pub fn wildcard_match_ignore_case(pattern: &str, text: &str) -> bool {
+    wildcard_match(&pattern.to_ascii_lowercase(), &text.to_ascii_lowercase())
+}
+
+// 比较时不提前返回，避免通过响应时间猜测 token、签名
+pub fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+// 转义 XML 文本中的特殊字符
+pub fn xml_escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+pub fn boxed_body<S, B>(body: S) -> BodyStream
+where
+    S: Stream<Item = Result<B, warp::Error>> + Send + 'static,
+    B: Buf,
+{
+    body.map_ok(|mut chunk| chunk.copy_to_bytes(chunk.remaining())).boxed()
+}
+
+// 解码路径中的百分号编码，非法的 UTF-8 替换为 U+FFFD
+pub fn decode(value: &str) -> String {
+    percent_decode_str(value).decode_utf8_lossy().into_owned()
+}
+
+// 从后端响应头中挑出需要透传给客户端的部分
+pub fn forwarded_headers(headers: &HeaderMap) -> HeaderMap {
+    let mut forwarded = HeaderMap::new();
+    for (name, value) in headers {
+        if FORWARD_HEADERS.contains(&name.as_str()) || name.as_str().starts_with("x-amz-meta-") {
+            forwarded.append(name.clone(), value.clone());
+        }
+    }
+    forwarded
+}
+
+// 读取请求体，超过上限时返回 413
+pub async fn read_body(mut body: BodyStream, headers: &HeaderMap, limit: u64) -> Result<Bytes, ProxyError> {
+    let too_large = || ProxyError::PayloadTooLarge(format!("Request body exceeds {} bytes", limit));
+    let content_length = headers
+        .get("content-length")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse::<u64>().ok());
+    if content_length.is_some_and(|length| length > limit) {
+        return Err(too_large());
+    }
+
+    let mut data = BytesMut::new();
+    while let Some(chunk) = body.next().await {
+        let chunk = chunk.map_err(|e| ProxyError::BadRequest(e.to_string()))?;
+        if (data.len() + chunk.len()) as u64 > limit {
+            return Err(too_large());
+        }
+        data.extend_from_slice(&chunk);
+    }
+    Ok(data.freeze())
+}
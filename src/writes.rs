@@ -0,0 +1,38 @@
+use crate::hot_cache;
+use crate::minio::minio_parser;
+use crate::replication;
+
+// 写入接口给出的对象 key：代理 PUT 使用公开 key，WebDAV、S3 接口使用存储中的 key
+pub enum WrittenKey<'a> {
+    Public(&'a str),
+    Stored(&'a str),
+}
+
+pub enum Change {
+    Put,
+    Delete,
+}
+
+// 所有写入成功后都经过这里：清除内存缓存，写入的对象再按 replication 配置入队复制。
+// 对象已经写入，入队失败不影响响应，只记录日志和 enqueue_errors 统计；删除不会复制
+pub async fn written(power: &str, bucket: &str, key: WrittenKey<'_>, change: Change) {
+    let (object_key, stored) = match key {
+        WrittenKey::Public(key) => {
+            hot_cache::purge(Some(power), Some(key), None);
+            (key, false)
+        }
+        WrittenKey::Stored(key) => {
+            hot_cache::purge_stored(power, key, false);
+            (key, true)
+        }
+    };
+    if matches!(change, Change::Delete) {
+        return;
+    }
+    // 默认桶不写入任务，目标 power 使用自己的默认桶
+    let default_bucket = minio_parser::get_minio_bucket_by_minio_config_key(power).await;
+    let route_bucket = if default_bucket.as_deref() == Some(bucket) { None } else { Some(bucket) };
+    if let Err(e) = replication::enqueue(power, route_bucket, object_key, stored).await {
+        log::error!("Written {}/{} of {} but replication is not queued: {}", bucket, object_key, power, e);
+    }
+}